@@ -1,32 +1,45 @@
-use std::old_io::{BufferedReader, IoError, IoErrorKind};
+use std::io::{self, BufReader, Bytes, Read};
 
-pub struct ByteStream<R: Reader> {
-    reader: BufferedReader<R>,
+/// Stream of the bytes of a source. Reading stops at the first error, which is
+/// kept to be taken with `take_error`, so a stream which stops early can be
+/// told apart from one which ended.
+pub struct ByteStream<R: Read> {
+    bytes: Bytes<BufReader<R>>,
+    error: Option<io::Error>,
 }
 
-impl<R: Reader> ByteStream<R> {
+impl<R: Read> ByteStream<R> {
+    /// Creates a `ByteStream` of the bytes read from `reader`, which it
+    /// buffers.
     #[inline]
     pub fn new(reader: R) -> ByteStream<R> {
         ByteStream {
-            reader: BufferedReader::new(reader)
+            bytes: BufReader::new(reader).bytes(),
+            error: None,
         }
     }
-}
 
-/// Return true if the io error is EOF.
-fn is_eof(err: &IoError) -> bool {
-    err.kind == IoErrorKind::EndOfFile
+    /// Returns the error which stopped the stream, if any.
+    pub fn take_error(&mut self) -> Option<io::Error> {
+        self.error.take()
+    }
 }
 
-impl<R: Reader> Iterator for ByteStream<R> {
+impl<R: Read> Iterator for ByteStream<R> {
     type Item = u8;
 
     #[inline]
     fn next(&mut self) -> Option<u8> {
-        match self.reader.read_byte() {
-            Ok(byte)                => Some(byte),
-            Err(ref e) if is_eof(e) => None,
-            Err(e)                  => panic!("IoError: {}!", e),
+        if self.error.is_some() {
+            return None;
+        }
+        match self.bytes.next() {
+            Some(Ok(byte)) => Some(byte),
+            Some(Err(e))   => {
+                self.error = Some(e);
+                None
+            },
+            None           => None,
         }
     }
 }
@@ -0,0 +1,95 @@
+use crate::syntax::{Ast, Dir, Ir, Span};
+
+/// Flat form of `Ir` executed by the interpreter, in which the bodies of loops
+/// are inlined and the matching bracket of each loop is resolved ahead of time.
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+pub enum Op {
+    /// Adds the value to the current cell.
+    Add(u64),
+    /// Subtracts the value from the current cell.
+    Sub(u64),
+    /// Shifts the pointer in memory left or right by a number of steps.
+    Shift(Dir, usize),
+    /// Reads and stores a single byte into the current cell.
+    Read,
+    /// Writes the byte at the current cell to `stdout` as a `char`.
+    Write,
+    /// Jumps past the `JumpIfNonZero` at the index if the current cell is 0.
+    JumpIfZero(usize),
+    /// Jumps back past the `JumpIfZero` at the index if the current cell is
+    /// not 0.
+    JumpIfNonZero(usize),
+    /// Dumps the pointer and the cells around it to `stderr`.
+    Dump,
+
+    // optimizations
+
+    /// Clears the current cell.
+    Clear,
+    /// Copies the value at the current cell to the cell left or right by a
+    /// number of steps.
+    Copy(Dir, usize),
+    /// Multiplies the value at the current cell by a specified factor and
+    /// then stores the product in the cell left or right by a number of steps.
+    Mul(Dir, usize, i64),
+    /// Scans left or right in memory until the value in the current cell is 0.
+    Scan(Dir),
+}
+
+/// An `Op` and the `Span` of source it was created from.
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+pub struct Instr {
+    /// The operation.
+    pub op: Op,
+    /// Source the operation was created from.
+    pub span: Span,
+}
+
+/// A flat sequence of `Instr`.
+pub type Bytecode = Vec<Instr>;
+
+/// Appends the lowered form of `ast` to `code`.
+fn lower_into(ast: &Ast, code: &mut Bytecode) {
+    for node in ast.iter() {
+        let op = match node.ir {
+            Ir::Add(value)              => Op::Add(value),
+            Ir::Sub(value)              => Op::Sub(value),
+            Ir::Shift(dir, steps)       => Op::Shift(dir, steps),
+            Ir::Read                    => Op::Read,
+            Ir::Write                   => Op::Write,
+            Ir::Dump                    => Op::Dump,
+            Ir::Clear                   => Op::Clear,
+            Ir::Copy(dir, steps)        => Op::Copy(dir, steps),
+            Ir::Mul(dir, steps, factor) => Op::Mul(dir, steps, factor),
+            Ir::Scan(dir)               => Op::Scan(dir),
+
+            // the jump over the body is patched once the index of its end is
+            // known
+            Ir::Loop(ref body) => {
+                let open = code.len();
+                code.push(Instr {
+                    op: Op::JumpIfZero(0),
+                    span: Span::at(node.span.start),
+                });
+
+                lower_into(body, code);
+
+                let close = code.len();
+                code.push(Instr {
+                    op: Op::JumpIfNonZero(open),
+                    span: Span::at(node.span.end),
+                });
+                code[open].op = Op::JumpIfZero(close);
+                continue;
+            },
+        };
+        code.push(Instr { op, span: node.span });
+    }
+}
+
+/// Lowers an `Ast` into `Bytecode`, resolving the jump targets of every loop.
+pub fn lower(ast: &Ast) -> Bytecode {
+    let mut code = Vec::new();
+    lower_into(ast, &mut code);
+    code
+}
@@ -0,0 +1,293 @@
+use std::fmt;
+use std::io::{self, BufRead, Write};
+
+use crate::eval::{Interpreter, Status};
+use crate::mem::DUMP_WINDOW;
+
+static HELP: &str = "\
+commands:
+  s, step [N]        execute the next N instructions, default 1
+  c, continue        run until a breakpoint, the end or more input is needed
+  b, break LINE:COL  stop at the instruction at a position in the source
+  b, break INDEX     stop at the instruction at an index in the bytecode
+  d, delete [N]      delete breakpoint N, or every breakpoint
+  l, list            list the breakpoints
+  t, tape [N]        print N cells either side of the pointer, default 8
+  p, pointer         print the pointer and the current cell
+  set VALUE          set the current cell to VALUE
+  w, where           show the next instruction and its position in the source
+  i, input TEXT      feed TEXT followed by a newline as input
+  eof                end the input
+  h, help            print this message
+  q, quit            stop debugging
+An empty line repeats the last command.
+";
+
+/// Place to stop a program being debugged.
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+pub enum Breakpoint {
+    /// Stops at an instruction whose span covers a line and column of source.
+    Source(usize, usize),
+    /// Stops at the instruction with an index in the `Bytecode`.
+    Instr(usize),
+}
+
+impl Breakpoint {
+    /// Parses a `Breakpoint` from `LINE:COL` or `INDEX`.
+    fn parse(s: &str) -> Option<Breakpoint> {
+        match s.find(':') {
+            Some(i) => {
+                let line = s[..i].parse().ok()?;
+                let col = s[i + 1..].parse().ok()?;
+                Some(Breakpoint::Source(line, col))
+            },
+            None => s.parse().ok().map(Breakpoint::Instr),
+        }
+    }
+}
+
+impl fmt::Display for Breakpoint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Breakpoint::Source(line, col) => write!(f, "{}:{}", line, col),
+            Breakpoint::Instr(index)      => write!(f, "instruction {}", index),
+        }
+    }
+}
+
+/// Interactive debugger of a program, which runs an `Interpreter` a step at a
+/// time as commands are read.
+pub struct Debugger {
+    interpreter: Interpreter,
+    lines: Vec<String>,           // lines of source, to show positions in
+    breakpoints: Vec<Breakpoint>,
+}
+
+impl Debugger {
+    /// Create a new `Debugger` of the program run by `interpreter`, which was
+    /// parsed from `source`.
+    pub fn new(interpreter: Interpreter, source: &[u8]) -> Debugger {
+        Debugger {
+            interpreter,
+            lines: String::from_utf8_lossy(source).lines()
+                                                  .map(String::from)
+                                                  .collect(),
+            breakpoints: Vec::new(),
+        }
+    }
+
+    /// Returns the `Interpreter` running the program.
+    pub fn interpreter(&self) -> &Interpreter {
+        &self.interpreter
+    }
+
+    /// Returns the breakpoints.
+    pub fn breakpoints(&self) -> &[Breakpoint] {
+        &self.breakpoints
+    }
+
+    /// Adds a breakpoint.
+    pub fn add_breakpoint(&mut self, breakpoint: Breakpoint) {
+        self.breakpoints.push(breakpoint);
+    }
+
+    /// Returns the number of the breakpoint at the next instruction, if any.
+    fn breakpoint(&self) -> Option<usize> {
+        let pc = self.interpreter.pc();
+        let span = match self.interpreter.code().get(pc) {
+            Some(instr) => instr.span,
+            None        => return None,
+        };
+        self.breakpoints.iter().position(|breakpoint| match *breakpoint {
+            Breakpoint::Source(line, col) => span.contains(line, col),
+            Breakpoint::Instr(index)      => index == pc,
+        })
+    }
+
+    /// Reads and executes commands from `commands` until it ends or the user
+    /// quits, writing the output of the program and of the commands to
+    /// `output`.
+    pub fn run<R, W>(&mut self, commands: &mut R, output: &mut W)
+                     -> io::Result<()>
+        where R: BufRead, W: Write {
+        writeln!(output, "{} instructions, type 'help' for a list of commands",
+                 self.interpreter.code().len())?;
+        self.show_position(output)?;
+
+        let mut last = String::new();
+        loop {
+            write!(output, "(bfi) ")?;
+            output.flush()?;
+            let mut line = String::new();
+            if commands.read_line(&mut line)? == 0 {
+                return writeln!(output);
+            }
+
+            // an empty line repeats the last command
+            let line = line.trim_end_matches(['\r', '\n']);
+            let command = if line.trim().is_empty() {
+                last.clone()
+            } else {
+                line.to_string()
+            };
+            if !self.command(&command, output)? {
+                return Ok(());
+            }
+            last = command;
+        }
+    }
+
+    /// Executes a single `command`. Returns false if the user quit.
+    fn command<W: Write>(&mut self, command: &str, output: &mut W)
+                         -> io::Result<bool> {
+        let mut words = command.split_whitespace();
+        let name = match words.next() {
+            Some(name) => name,
+            None       => return Ok(true),
+        };
+        let arg = words.next();
+
+        match name {
+            "s" | "step" => match arg.map(|n| n.parse::<usize>()) {
+                None         => self.resume(Some(1), output)?,
+                Some(Ok(n))  => self.resume(Some(n), output)?,
+                Some(Err(_)) => writeln!(output, "invalid number of steps")?,
+            },
+            "c" | "continue" => self.resume(None, output)?,
+            "b" | "break" => match arg.and_then(Breakpoint::parse) {
+                Some(breakpoint) => {
+                    self.breakpoints.push(breakpoint);
+                    writeln!(output, "breakpoint {} at {}",
+                             self.breakpoints.len() - 1, breakpoint)?;
+                },
+                None => writeln!(output, "expected LINE:COL or INDEX")?,
+            },
+            "d" | "delete" => match arg.map(|n| n.parse::<usize>()) {
+                None => self.breakpoints.clear(),
+                Some(Ok(n)) if n < self.breakpoints.len() => {
+                    self.breakpoints.remove(n);
+                },
+                Some(_) => writeln!(output, "no such breakpoint")?,
+            },
+            "l" | "list" => {
+                for (n, breakpoint) in self.breakpoints.iter().enumerate() {
+                    writeln!(output, "{}: {}", n, breakpoint)?;
+                }
+            },
+            "t" | "tape" => match arg.map(|n| n.parse::<usize>()) {
+                None         => self.show_tape(DUMP_WINDOW, output)?,
+                Some(Ok(n))  => self.show_tape(n, output)?,
+                Some(Err(_)) => writeln!(output, "invalid number of cells")?,
+            },
+            "p" | "pointer" => {
+                let mem = self.interpreter.mem();
+                writeln!(output, "pointer at cell {}, which holds {}",
+                         mem.position(), mem.get())?;
+            },
+            "set" => match arg.map(|n| n.parse::<u64>()) {
+                Some(Ok(value)) => self.interpreter.mem_mut().set(value),
+                _ => writeln!(output, "expected a value")?,
+            },
+            "w" | "where" => self.show_position(output)?,
+            "i" | "input" => {
+                let text = command.trim_start()[name.len()..].trim_start();
+                self.interpreter.feed_input(text.as_bytes());
+                self.interpreter.feed_input(b"\n");
+            },
+            "eof" => self.interpreter.close_input(),
+            "h" | "help" => write!(output, "{}", HELP)?,
+            "q" | "quit" => return Ok(false),
+            _ => {
+                writeln!(output, "unknown command '{}', type 'help' for a list \
+                                  of commands", name)?;
+            },
+        }
+        Ok(true)
+    }
+
+    /// Runs the program for `steps` instructions, or until it reaches a
+    /// breakpoint if `None`, then shows where it stopped.
+    fn resume<W: Write>(&mut self, steps: Option<usize>, output: &mut W)
+                        -> io::Result<()> {
+        let mut remaining = steps;
+        let mut first = true;
+        loop {
+            if remaining == Some(0) {
+                break;
+            }
+
+            // the breakpoint it starts at, if any, was already reported
+            if !first {
+                if let Some(n) = self.breakpoint() {
+                    writeln!(output, "stopped at breakpoint {}", n)?;
+                    break;
+                }
+            }
+            first = false;
+
+            let status = self.interpreter.step();
+            self.interpreter.write_pending_lines(output)?;
+
+            match status {
+                Status::OutOfFuel  => {},
+                Status::Halted     => break,
+                Status::NeedsInput => {
+                    writeln!(output, "waiting for input, use 'input TEXT' or \
+                                      'eof'")?;
+                    break;
+                },
+                Status::Error(e) => {
+                    writeln!(output, "error: {}", e)?;
+                    break;
+                },
+            }
+            if let Some(n) = remaining.as_mut() {
+                *n -= 1;
+            }
+        }
+        self.show_position(output)
+    }
+
+    /// Shows the next instruction, with the line of source it was created
+    /// from and the span of the instruction marked beneath it.
+    fn show_position<W: Write>(&self, output: &mut W) -> io::Result<()> {
+        let pc = self.interpreter.pc();
+        let instr = match self.interpreter.code().get(pc) {
+            Some(instr) => instr,
+            None => {
+                return writeln!(output, "halted after {} steps",
+                                self.interpreter.steps());
+            },
+        };
+        writeln!(output, "{}: instruction {}: {:?}", instr.span, pc, instr.op)?;
+
+        let start = instr.span.start;
+        if let Some(line) = self.lines.get(start.line - 1) {
+            let end = if instr.span.end.line == start.line {
+                instr.span.end.col
+            } else {
+                line.len()
+            };
+            let marker = "^".repeat(end.saturating_sub(start.col) + 1);
+            writeln!(output, "    {}", line)?;
+            writeln!(output, "    {}{}", " ".repeat(start.col - 1), marker)?;
+        }
+        Ok(())
+    }
+
+    /// Shows the `window` cells either side of the pointer, marking the
+    /// current cell.
+    fn show_tape<W: Write>(&self, window: usize, output: &mut W)
+                           -> io::Result<()> {
+        let mem = self.interpreter.mem();
+        let pointer = mem.position();
+        let window = window as isize;
+        for position in pointer - window..=pointer + window {
+            if let Some(value) = mem.cell(position) {
+                let marker = if position == pointer { ">" } else { " " };
+                writeln!(output, "{} {:>6}: {}", marker, position, value)?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -1,156 +1,509 @@
-use std::old_io::{File, IoResult};
+use std::cmp;
+use std::fs::File;
+use std::io::{self, Write};
 
-use syntax::{Ast, Ir, Left, Right};
+use crate::mem::{Config, Eof, Overflow, DUMP_WINDOW};
+use crate::syntax::{Ast, Ir, Left, Right};
 
-/// Helper function to check that an IoResult is not Err.
-fn check_io_result(io_result: &IoResult<()>) {
-    match *io_result {
-        Ok(_)      => {},
-        Err(ref e) => panic!("{}", e),
+/// Returns the indentation for a line nested `depth` levels deep.
+fn indent(depth: usize) -> String {
+    let mut indent = String::new();
+    for _ in 0..depth {
+        indent.push_str("    ");
     }
+    indent
 }
 
-/// Emits a file which contains the optmized `Ast`.
-pub fn emit_ir(file_name: &str, ast: &Ast) {
+/// Writes each instruction of `ast` as a line, nesting the bodies of loops.
+fn write_ir(file: &mut File, ast: &Ast, depth: usize) -> io::Result<()> {
+    for node in ast.iter() {
+        let line = match node.ir {
+            Ir::Loop(_) => format!("{}{}: Loop", indent(depth), node.span),
+            ref ir      => format!("{}{}: {:?}", indent(depth), node.span, *ir),
+        };
+        writeln!(file, "{}", line)?;
+
+        if let Ir::Loop(ref body) = node.ir {
+            write_ir(file, body, depth + 1)?;
+        }
+    }
+    Ok(())
+}
+
+/// Emits a file which contains the optmized `Ast`, one instruction per line
+/// prefixed by the span of source it was created from. Fails if the file can
+/// not be created or written.
+pub fn emit_ir(file_name: &str, ast: &Ast) -> io::Result<()> {
     let ir_file_name = &(file_name.to_string() + ".ir")[..];
-    let mut file = match File::create(&Path::new(ir_file_name)) {
-        Ok(file) => file,
-        Err(e)       => panic!("{}", e),
-    };
+    let mut file = File::create(ir_file_name)?;
+
+    write_ir(&mut file, ast, 0)
+}
+
+/// The helper functions an emitted program calls, so that only those are
+/// written and compilers do not warn of the rest being unused.
+#[derive(Default)]
+struct Helpers {
+    add: bool,    // add to a cell, by `Ir::Add`, copies and muls
+    sub: bool,    // subtract from a cell, by `Ir::Sub` and negative muls
+    mul: bool,    // multiply a cell, by muls with a factor other than 1 or -1
+    input: bool,  // read a cell, by `Ir::Read`
+    output: bool, // write a cell, by `Ir::Write`
+    dump: bool,   // dump the tape, by `Ir::Dump`
+}
+
+impl Helpers {
+    /// Returns the helpers called by `ast` and the bodies of its loops.
+    fn of(ast: &Ast) -> Helpers {
+        let mut helpers: Helpers = Default::default();
+        helpers.find(ast);
+        helpers
+    }
+
+    /// Marks the helpers called by `ast` and the bodies of its loops.
+    fn find(&mut self, ast: &Ast) {
+        for node in ast.iter() {
+            match node.ir {
+                Ir::Add(_)            => self.add = true,
+                Ir::Sub(_)            => self.sub = true,
+                Ir::Read              => self.input = true,
+                Ir::Write             => self.output = true,
+                Ir::Dump              => self.dump = true,
+                Ir::Copy(..)          => self.add = true,
+                Ir::Mul(_, _, factor) => {
+                    if factor < 0 {
+                        self.sub = true;
+                    } else {
+                        self.add = true;
+                    }
+                    self.mul |= factor.unsigned_abs() != 1;
+                },
+                Ir::Loop(ref body)    => self.find(body),
+                _                     => {},
+            }
+        }
+    }
 
-    for ir in ast.iter() {
-        let io_result = file.write_line(&format!("{:?}", *ir)[..]);
-        check_io_result(&io_result);
+    /// Returns true if any cell arithmetic is done, which may overflow.
+    fn arithmetic(&self) -> bool {
+        self.add || self.sub || self.mul
     }
 }
 
-/// Emits a C file with `file_name` created from `ast`.
-pub fn emit_c(file_name: &str, ast: &Ast) {
-    let c_file_name = &(file_name.to_string() + ".c")[..];
-    let mut file = match File::create(&Path::new(c_file_name)) {
-        Ok(file) => file,
-        Err(e)       => panic!("{}", e),
+/// Returns true if `ast` only moves the pointer, if that, so the emitted
+/// program must read `mem` and `p` itself for them to count as used.
+fn tapeless(ast: &Ast) -> bool {
+    ast.iter().all(|node| matches!(node.ir, Ir::Shift(..)))
+}
+
+/// Returns the `add`, `sub` and `mul` functions of `fns` which are called by
+/// `helpers`, after the `overflow` function if the `Overflow::Error` policy
+/// needs it.
+fn cell_fns(overflow: Overflow, helpers: &Helpers, overflow_fn: &str,
+            fns: [&str; 3]) -> String {
+    let mut cell_fns = String::new();
+    if overflow == Overflow::Error && helpers.arithmetic() {
+        cell_fns.push_str(overflow_fn);
+    }
+    for (used, f) in [helpers.add, helpers.sub, helpers.mul].iter().zip(fns) {
+        if *used {
+            cell_fns.push_str(f);
+        }
+    }
+    cell_fns
+}
+
+/// Returns `value` as an unsigned 64 bit C literal.
+fn c_literal(value: u64) -> String {
+    format!("{}ULL", value)
+}
+
+/// Returns a C statement which adds the product of the current cell and
+/// `factor` to the cell at `index`. Like `Mem::multiply`, and the loop it
+/// replaces, it does not touch the cell at `index` when the current cell is 0,
+/// as that cell may be off the tape.
+fn c_mul(index: &str, factor: i64) -> String {
+    let mul = match factor {
+        1  | -1 => "mem[p]".to_string(),
+        _       => format!("mul(mem[p], {})", c_literal(factor.unsigned_abs())),
     };
+    let op = if factor < 0 { "sub" } else { "add" };
+    format!("if (mem[p] != 0) {{ mem[{0}] = {1}(mem[{0}], {2}); }}", index, op,
+            mul)
+}
 
-    // save the result values from each write
-    let mut io_result;
+/// Returns the C functions `add`, `sub` and `mul` called by `helpers`, which
+/// do arithmetic on cells according to the `Overflow` policy.
+fn c_cell_fns(overflow: Overflow, helpers: &Helpers) -> String {
+    let fns = match overflow {
+        Overflow::Wrap => [
+            "static cell add(cell a, uint64_t b) { return (cell)(a + b); }\n",
+            "static cell sub(cell a, uint64_t b) { return (cell)(a - b); }\n",
+            "static uint64_t mul(cell a, uint64_t b) { return a * b; }\n",
+        ],
+        Overflow::Saturate => ["\
+        static cell add(cell a, uint64_t b) {\n\
+            return b > CELL_MAX - a ? CELL_MAX : (cell)(a + b);\n\
+        }\n\
+        ", "\
+        static cell sub(cell a, uint64_t b) {\n\
+            return b > a ? 0 : (cell)(a - b);\n\
+        }\n\
+        ", "\
+        static uint64_t mul(cell a, uint64_t b) {\n\
+            return b != 0 && a > UINT64_MAX / b ? UINT64_MAX : a * b;\n\
+        }\n\
+        "],
+        Overflow::Error => ["\
+        static cell add(cell a, uint64_t b) {\n\
+            if (b > CELL_MAX - a) overflow(\"cell overflow\");\n\
+            return (cell)(a + b);\n\
+        }\n\
+        ", "\
+        static cell sub(cell a, uint64_t b) {\n\
+            if (b > a) overflow(\"cell underflow\");\n\
+            return (cell)(a - b);\n\
+        }\n\
+        ", "\
+        static uint64_t mul(cell a, uint64_t b) {\n\
+            if (b != 0 && a > UINT64_MAX / b) overflow(\"cell overflow\");\n\
+            return a * b;\n\
+        }\n\
+        "],
+    };
+    cell_fns(overflow, helpers, "\
+    static void overflow(const char *msg) {\n\
+        fprintf(stderr, \"error: %s\\n\", msg);\n\
+        exit(1);\n\
+    }\n\
+    ", fns)
+}
 
-    // standard includes, main function and mem/p declarations
-    io_result = file.write_str("\
-    #include <stdio.h>\n\
-    #include <stdint.h>\n\
-    #include <stdlib.h>\n\
-    \n\
-    int main(int argc, char **argv) {\n\
-    uint8_t mem[65536] = {0};\n\
-    uint32_t p = 0;\n\
-    ");
-    check_io_result(&io_result);
+/// Returns the C function `input`, which reads a byte from stdin, or returns
+/// the value given by the `Eof` policy at the end of input.
+fn c_input_fn(eof: Eof) -> String {
+    let eof_value = match eof {
+        Eof::Zero      => "0",
+        Eof::MinusOne  => "CELL_MAX",
+        Eof::Unchanged => "old",
+    };
+    format!("\
+    static cell input(cell old) {{\n\
+        int c = getchar();\n\
+        return c == EOF ? {} : (cell)c;\n\
+    }}\n\
+    ", eof_value)
+}
 
-    // write each ir as a line
-    for ir in ast.iter() {
-        let ir_str = match *ir {
-            Ir::Add(value)          => format!("mem[p] += {};", value),
-            Ir::Sub(value)          => format!("mem[p] -= {};", value),
+/// The C function `dump`, which writes the pointer and the `DUMP_WINDOW`
+/// cells either side of it to stderr, as the interpreter does for `Ir::Dump`.
+/// The emitted tape is fixed, so the index `p` it prints is the same as
+/// `Mem::position`.
+fn c_dump_fn() -> String {
+    format!("\
+static void dump(size_t p, const char *pos) {{
+    size_t last = sizeof mem / sizeof mem[0] - 1;
+    size_t i = p < {0} ? 0 : p - {0};
+    size_t end = last - p < {0} ? last : p + {0};
+    fflush(stdout);
+    fprintf(stderr, \"%s: pointer at cell %zu:\", pos, p);
+    for (; i <= end; i++) {{
+        fprintf(stderr, i == p ? \" [%llu]\" : \" %llu\",
+                (unsigned long long)mem[i]);
+    }}
+    fprintf(stderr, \"\\n\");
+}}
+", DUMP_WINDOW)
+}
+
+/// Writes each instruction of `ast` as a line of C.
+fn write_c(file: &mut File, ast: &Ast, depth: usize) -> io::Result<()> {
+    for node in ast.iter() {
+        let ir_str = match node.ir {
+            Ir::Add(value) => format!("mem[p] = add(mem[p], {});", c_literal(value)),
+            Ir::Sub(value) => format!("mem[p] = sub(mem[p], {});", c_literal(value)),
             Ir::Shift(Left, steps)  => format!("p -= {};", steps),
             Ir::Shift(Right, steps) => format!("p += {};", steps),
-            Ir::Read                => "mem[p] = getchar();".to_string(),
+            Ir::Read                => "mem[p] = input(mem[p]);".to_string(),
             Ir::Write               => "putchar(mem[p]);".to_string(),
-            Ir::Open                => "while (mem[p] != 0) {".to_string(),
-            Ir::Close               => "}".to_string(),
+            Ir::Dump => format!("dump(p, \"{}\");", node.span),
+            Ir::Loop(_)             => "while (mem[p] != 0) {".to_string(),
 
             // optimizations
             Ir::Clear               => "mem[p] = 0;".to_string(),
             Ir::Scan(Left)          => "while (mem[p] != 0) { p -= 1; }".to_string(),
             Ir::Scan(Right)         => "while (mem[p] != 0) { p += 1; }".to_string(),
-            Ir::Copy(Left, steps)   => format!("mem[p - {}] += mem[p];", steps),
-            Ir::Copy(Right, steps)  => format!("mem[p + {}] += mem[p];", steps),
+            Ir::Copy(Left, steps)   => c_mul(&format!("p - {}", steps)[..], 1),
+            Ir::Copy(Right, steps)  => c_mul(&format!("p + {}", steps)[..], 1),
             Ir::Mul(Left, steps, factor) => {
-                format!("mem[p - {}] += mem[p] * {};", steps, factor)
+                c_mul(&format!("p - {}", steps)[..], factor)
             },
             Ir::Mul(Right, steps, factor) => {
-                format!("mem[p + {}] += mem[p] * {};", steps, factor)
+                c_mul(&format!("p + {}", steps)[..], factor)
             },
-        } + "\n";
+        };
+        writeln!(file, "{}{}", indent(depth), ir_str)?;
 
-        io_result = file.write_all(ir_str.as_bytes());
-        check_io_result(&io_result);
+        // write the body of a loop and then close it
+        if let Ir::Loop(ref body) = node.ir {
+            write_c(file, body, depth + 1)?;
+            writeln!(file, "{}}}", indent(depth))?;
+        }
     }
+    Ok(())
+}
+
+/// Emits a C file with `file_name` created from `ast`, which runs on a tape of
+/// the size, cell width, overflow and EOF policies given by `config`. Fails if
+/// the file can not be created or written.
+pub fn emit_c(file_name: &str, ast: &Ast, config: &Config) -> io::Result<()> {
+    let c_file_name = &(file_name.to_string() + ".c")[..];
+    let mut file = File::create(c_file_name)?;
+
+    // standard includes, cell type and mem declaration
+    file.write_all(format!("\
+    #include <stdio.h>\n\
+    #include <stdint.h>\n\
+    #include <stdlib.h>\n\
+    \n\
+    typedef uint{}_t cell;\n\
+    #define CELL_MAX {}\n\
+    \n\
+    static cell mem[{}];\n\
+    \n\
+    ", config.cell_width.bits(), c_literal(config.cell_width.max()),
+       cmp::max(config.tape_size, 1)).as_bytes())?;
+
+    // cell arithmetic, input, dump, main function and p declaration
+    let helpers = Helpers::of(ast);
+    file.write_all(c_cell_fns(config.overflow, &helpers).as_bytes())?;
+    if helpers.input {
+        file.write_all(c_input_fn(config.eof).as_bytes())?;
+    }
+    if helpers.dump {
+        file.write_all(c_dump_fn().as_bytes())?;
+    }
+    file.write_all(b"\
+    \n\
+    int main(int argc, char **argv) {\n\
+    size_t p = 0;\n\
+    ")?;
+
+    // write each ir as a line
+    write_c(&mut file, ast, 1)?;
 
     // close the main function
-    io_result = file.write_str("}");
-    check_io_result(&io_result);
+    if tapeless(ast) {
+        file.write_all(b"    (void)mem;\n    (void)p;\n")?;
+    }
+    file.write_all(b"}\n")
 }
 
-/// Emits a Rust file with `file_name` created from `ast`.
-pub fn emit_rust(file_name: &str, ast: &Ast) {
-    let rs_file_name = &(file_name.to_string() + ".rs")[..];
-    let mut file = match File::create(&Path::new(rs_file_name)) {
-        Ok(file) => file,
-        Err(e)       => panic!("{}", e),
+/// Returns a Rust statement which adds the product of the current cell and
+/// `factor` to the cell at `index`, unless the current cell is 0, like
+/// `c_mul`.
+fn rust_mul(index: &str, factor: i64) -> String {
+    let mul = match factor {
+        1  | -1 => "mem[p] as u64".to_string(),
+        _       => format!("mul(mem[p], {})", factor.abs()),
     };
+    let op = if factor < 0 { "sub" } else { "add" };
+    format!("if mem[p] != 0 {{ mem[{0}] = {1}(mem[{0}], {2}); }}", index, op,
+            mul)
+}
 
-    // save the result values from each write
-    let mut io_result;
-
-    // standard includes, main function and mem/p declarations
-    io_result = file.write_str("\
-    #![allow(unstable)]\n\
-    \n\
-    /// Reads a `char` from `stdin`.\n\
-    fn read_char() -> char {\n\
-        match std::io::stdin().read_char() {\n\
-            Ok(c)  => c,\n\
-            Err(e) => panic!(\"{}\", e),\n\
+/// Returns the Rust functions `add`, `sub` and `mul` called by `helpers`,
+/// which do arithmetic on cells according to the `Overflow` policy.
+fn rust_cell_fns(overflow: Overflow, helpers: &Helpers) -> String {
+    let fns = match overflow {
+        Overflow::Wrap => [
+            "fn add(a: Cell, b: u64) -> Cell { (a as u64).wrapping_add(b) as Cell }\n",
+            "fn sub(a: Cell, b: u64) -> Cell { (a as u64).wrapping_sub(b) as Cell }\n",
+            "fn mul(a: Cell, b: u64) -> u64 { (a as u64).wrapping_mul(b) }\n",
+        ],
+        Overflow::Saturate => ["\
+        fn add(a: Cell, b: u64) -> Cell {\n\
+            let sum = (a as u64).saturating_add(b);\n\
+            if sum > CELL_MAX { CELL_MAX as Cell } else { sum as Cell }\n\
         }\n\
-    }\n\
-    \n\
-    /// Writes a `char` to `stdout`.\n\
-    fn write_char(c: char) {\n\
-        match std::io::stdout().write_char(c) {\n\
-            Ok(_)  => {},\n\
-            Err(e) => panic!(\"{}\", e),\n\
+        ",
+        "fn sub(a: Cell, b: u64) -> Cell { (a as u64).saturating_sub(b) as Cell }\n",
+        "fn mul(a: Cell, b: u64) -> u64 { (a as u64).saturating_mul(b) }\n",
+        ],
+        Overflow::Error => ["\
+        fn add(a: Cell, b: u64) -> Cell {\n\
+            match (a as u64).checked_add(b) {\n\
+                Some(sum) if sum <= CELL_MAX => sum as Cell,\n\
+                _ => overflow(\"cell overflow\"),\n\
+            }\n\
+        }\n\
+        ", "\
+        fn sub(a: Cell, b: u64) -> Cell {\n\
+            match (a as u64).checked_sub(b) {\n\
+                Some(difference) => difference as Cell,\n\
+                None => overflow(\"cell underflow\"),\n\
+            }\n\
         }\n\
+        ", "\
+        fn mul(a: Cell, b: u64) -> u64 {\n\
+            match (a as u64).checked_mul(b) {\n\
+                Some(product) => product,\n\
+                None => overflow(\"cell overflow\"),\n\
+            }\n\
+        }\n\
+        "],
+    };
+    cell_fns(overflow, helpers, "\
+    fn overflow(msg: &str) -> ! {\n\
+        std::io::stdout().flush().unwrap();\n\
+        eprintln!(\"error: {}\", msg);\n\
+        std::process::exit(1);\n\
     }\n\
-    \n\
-    fn main() {\n\
-    let mut mem = [0u8; 65_536us];\n\
-    let mut p = 0us;\n\
-    \n\
-    ");
-    check_io_result(&io_result);
+    ", fns)
+}
 
-    // write each ir as a line
-    for ir in ast.iter() {
-        let ir_str = match *ir {
-            Ir::Add(value)          => format!("mem[p] += {};", value),
-            Ir::Sub(value)          => format!("mem[p] -= {};", value),
+/// Returns the Rust function `input`, which reads a byte from stdin, or
+/// returns the value given by the `Eof` policy at the end of input.
+fn rust_input_fn(eof: Eof) -> String {
+    // the old value of the cell is named `_old` when it is not used, which
+    // rustc would otherwise warn of
+    let (old, eof_value) = match eof {
+        Eof::Zero      => ("_old", "0"),
+        Eof::MinusOne  => ("_old", "CELL_MAX as Cell"),
+        Eof::Unchanged => ("old", "old"),
+    };
+    format!("\
+    fn input({}: Cell) -> Cell {{\n\
+        std::io::stdout().flush().unwrap();\n\
+        let mut buf = [0u8];\n\
+        match std::io::stdin().read(&mut buf) {{\n\
+            Ok(1) => buf[0] as Cell,\n\
+            Ok(_) => {},\n\
+            Err(e) => panic!(\"{{}}\", e),\n\
+        }}\n\
+    }}\n\
+    ", old, eof_value)
+}
+
+/// The Rust function `dump`, which writes the pointer and the `DUMP_WINDOW`
+/// cells either side of it to stderr, as the interpreter does for `Ir::Dump`.
+/// Like the C one, it prints the index `p`, which is the same as
+/// `Mem::position`.
+fn rust_dump_fn() -> String {
+    format!("\
+fn dump(mem: &[Cell], p: usize, pos: &str) {{
+    std::io::stdout().flush().unwrap();
+    let end = std::cmp::min(p + {0}, mem.len() - 1);
+    let cells: Vec<String> = (p.saturating_sub({0})..=end).map(|i| {{
+        if i == p {{
+            format!(\"[{{}}]\", mem[i])
+        }} else {{
+            mem[i].to_string()
+        }}
+    }}).collect();
+    eprintln!(\"{{}}: pointer at cell {{}}: {{}}\", pos, p, cells.join(\" \"));
+}}
+", DUMP_WINDOW)
+}
+
+/// Writes each instruction of `ast` as a line of Rust.
+fn write_rust(file: &mut File, ast: &Ast, depth: usize) -> io::Result<()> {
+    for node in ast.iter() {
+        let ir_str = match node.ir {
+            Ir::Add(value)          => format!("mem[p] = add(mem[p], {});", value),
+            Ir::Sub(value)          => format!("mem[p] = sub(mem[p], {});", value),
             Ir::Shift(Left, steps)  => format!("p -= {};", steps),
             Ir::Shift(Right, steps) => format!("p += {};", steps),
-            Ir::Read                => "mem[p] = read_char() as u8;".to_string(),
-            Ir::Write               => "write_char(mem[p] as char);".to_string(),
-            Ir::Open                => "while mem[p] != 0 {".to_string(),
-            Ir::Close               => "}".to_string(),
+            Ir::Read                => "mem[p] = input(mem[p]);".to_string(),
+            Ir::Write               => "output(mem[p]);".to_string(),
+            Ir::Dump => format!("dump(&mem, p, \"{}\");", node.span),
+            Ir::Loop(_)             => "while mem[p] != 0 {".to_string(),
 
             // optimizations
             Ir::Clear               => "mem[p] = 0;".to_string(),
             Ir::Scan(Left)          => "while mem[p] != 0 { p -= 1; }".to_string(),
             Ir::Scan(Right)         => "while mem[p] != 0 { p += 1; }".to_string(),
-            Ir::Copy(Left, steps)   => format!("mem[p - {}] += mem[p];", steps),
-            Ir::Copy(Right, steps)  => format!("mem[p + {}] += mem[p];", steps),
+            Ir::Copy(Left, steps)   => rust_mul(&format!("p - {}", steps)[..], 1),
+            Ir::Copy(Right, steps)  => rust_mul(&format!("p + {}", steps)[..], 1),
             Ir::Mul(Left, steps, factor) => {
-                format!("mem[p - {}] += mem[p] * {};", steps, factor)
+                rust_mul(&format!("p - {}", steps)[..], factor)
             },
             Ir::Mul(Right, steps, factor) => {
-                format!("mem[p + {}] += mem[p] * {};", steps, factor)
+                rust_mul(&format!("p + {}", steps)[..], factor)
             },
-        } + "\n";
+        };
+        writeln!(file, "{}{}", indent(depth), ir_str)?;
+
+        // write the body of a loop and then close it
+        if let Ir::Loop(ref body) = node.ir {
+            write_rust(file, body, depth + 1)?;
+            writeln!(file, "{}}}", indent(depth))?;
+        }
+    }
+    Ok(())
+}
+
+/// Emits a Rust file with `file_name` created from `ast`, which runs on a tape
+/// of the size, cell width, overflow and EOF policies given by `config`. Fails
+/// if the file can not be created or written.
+pub fn emit_rust(file_name: &str, ast: &Ast, config: &Config)
+                 -> io::Result<()> {
+    let rs_file_name = &(file_name.to_string() + ".rs")[..];
+    let mut file = File::create(rs_file_name)?;
+
+    // rustc may see that a copy or mul which is never run would index off the
+    // tape, and refuse to compile it
+    file.write_all(b"#![allow(unused_mut, arithmetic_overflow)]\n\n")?;
+
+    // io traits of the helpers which read, write or flush
+    let helpers = Helpers::of(ast);
+    let overflow = config.overflow == Overflow::Error && helpers.arithmetic();
+    if helpers.input {
+        file.write_all(b"use std::io::Read;\n")?;
+    }
+    if helpers.input || helpers.output || helpers.dump || overflow {
+        file.write_all(b"use std::io::Write;\n")?;
+    }
+
+    // output function, cell type and, where it is used, its max value
+    if helpers.output {
+        file.write_all(b"\
+        \n\
+        /// Writes the low byte of a cell to `stdout`.\n\
+        fn output(c: Cell) {\n\
+            std::io::stdout().write_all(&[c as u8]).unwrap();\n\
+        }\n\
+        ")?;
+    }
+    writeln!(file, "\ntype Cell = u{};", config.cell_width.bits())?;
+    if helpers.add && config.overflow != Overflow::Wrap
+        || helpers.input && config.eof == Eof::MinusOne {
+        writeln!(file, "const CELL_MAX: u64 = {};", config.cell_width.max())?;
+    }
+    writeln!(file)?;
 
-        io_result = file.write_all(ir_str.as_bytes());
-        check_io_result(&io_result);
+    // cell arithmetic, input, dump, main function and mem/p declarations
+    file.write_all(rust_cell_fns(config.overflow, &helpers).as_bytes())?;
+    if helpers.input {
+        file.write_all(rust_input_fn(config.eof).as_bytes())?;
     }
+    if helpers.dump {
+        file.write_all(rust_dump_fn().as_bytes())?;
+    }
+    file.write_all(format!("\
+    \n\
+    fn main() {{\n\
+    let mut mem: Vec<Cell> = std::iter::repeat(0).take({}).collect();\n\
+    let mut p = 0usize;\n\
+    \n\
+    ", cmp::max(config.tape_size, 1)).as_bytes())?;
+
+    // write each ir as a line
+    write_rust(&mut file, ast, 1)?;
 
     // close the main function
-    io_result = file.write_str("}");
-    check_io_result(&io_result);
+    if tapeless(ast) {
+        file.write_all(b"    let _ = (&mem, p);\n")?;
+    }
+    file.write_all(b"}\n")
 }
@@ -0,0 +1,54 @@
+use std::fmt;
+use std::io;
+
+use crate::eval::RuntimeError;
+use crate::parser::ParseError;
+
+/// Error of any stage of reading, running or emitting a program.
+#[derive(Debug)]
+pub enum Error {
+    /// The source did not parse.
+    Parse(ParseError),
+    /// The program failed while it ran, or was stopped by one of its `Limits`.
+    Runtime(RuntimeError),
+    /// Reading or writing a file or stream failed.
+    Io(io::Error),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::Parse(ref e)   => write!(f, "{}: {}", e.position(), e),
+            Error::Runtime(ref e) => write!(f, "{}", e),
+            Error::Io(ref e)      => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match *self {
+            Error::Parse(ref e)   => Some(e),
+            Error::Runtime(ref e) => Some(e),
+            Error::Io(ref e)      => Some(e),
+        }
+    }
+}
+
+impl From<ParseError> for Error {
+    fn from(e: ParseError) -> Error {
+        Error::Parse(e)
+    }
+}
+
+impl From<RuntimeError> for Error {
+    fn from(e: RuntimeError) -> Error {
+        Error::Runtime(e)
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Error {
+        Error::Io(e)
+    }
+}
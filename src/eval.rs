@@ -1,83 +1,669 @@
-use std::old_io::{stdin, stdout};
+use std::cmp;
+use std::collections::VecDeque;
+use std::fmt;
+use std::io::{self, BufRead, Write};
+use std::mem;
+use std::str;
+use std::time::{Duration, Instant};
 
-use mem::Mem;
-use syntax::{Ast, Ir};
+use crate::bytecode::{lower, Bytecode, Op};
+use crate::mem::{Config, Mem, MemError, DUMP_WINDOW};
+use crate::syntax::{Ast, Position, Span};
 
-/// Reads a `char` from `stdin`.
-fn read_char() -> char {
-    match stdin().read_char() {
-        Ok(c)  => c,
-        Err(e) => panic!("{}", e),
+/// How the `,` and `.` instructions convert between cells and the bytes of
+/// input and output.
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+pub enum Encoding {
+    /// Each cell is read or written as a single raw byte.
+    Bytes,
+    /// Each cell holds the code point of a `char`, which is read or written
+    /// UTF-8 encoded.
+    Utf8,
+}
+
+/// Returns the number of bytes in a UTF-8 encoded `char` which starts with the
+/// byte `first`, or `None` if no `char` starts with it.
+fn utf8_width(first: u8) -> Option<usize> {
+    match first {
+        0x00..=0x7f => Some(1),
+        0xc0..=0xdf => Some(2),
+        0xe0..=0xef => Some(3),
+        0xf0..=0xf7 => Some(4),
+        _           => None,
     }
 }
 
-/// Writes a `char` to `stdout`.
-fn write_char(c: char) {
-    match stdout().write_char(c) {
-        Ok(_)  => {},
-        Err(e) => panic!("{}", e),
+/// Returns the error for input which is not valid UTF-8.
+fn invalid_utf8() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData,
+                   "stream did not contain valid UTF-8")
+}
+
+/// Reads a cell from the front of `input`. Returns `Ok(None)` if `input`
+/// does not hold a whole cell yet, or an error if it never will.
+fn read(input: &mut VecDeque<u8>, encoding: Encoding)
+        -> io::Result<Option<u64>> {
+    let first = match input.front() {
+        Some(&byte) => byte,
+        None        => return Ok(None),
+    };
+    match encoding {
+        Encoding::Bytes => {
+            input.pop_front();
+            Ok(Some(first as u64))
+        },
+        Encoding::Utf8 => {
+            let width = utf8_width(first).ok_or_else(invalid_utf8)?;
+            if input.len() < width {
+                return Ok(None);
+            }
+            let buf: Vec<u8> = input.drain(..width).collect();
+            match str::from_utf8(&buf) {
+                Ok(s)  => Ok(s.chars().next().map(|c| c as u64)),
+                Err(_) => Err(invalid_utf8()),
+            }
+        },
     }
 }
 
-/// Evaluates an `Ast` iteratively.
-pub fn eval(ast: &Ast) {
-    // allocated memory
-    let mut mem = Mem::new();
-    // stack of previous loop open indexes
-    let mut stack = Vec::new();
+/// Writes a cell to `output`. In UTF-8 mode a cell that is not a valid code
+/// point is written as U+FFFD.
+fn write(output: &mut Vec<u8>, encoding: Encoding, value: u64) {
+    match encoding {
+        Encoding::Bytes => output.push(value as u8),
+        Encoding::Utf8  => {
+            let c = u32::try_from(value).ok()
+                                        .and_then(char::from_u32)
+                                        .unwrap_or('\u{fffd}');
+            output.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes());
+        },
+    }
+}
 
-    let mut i = 0us;
-    while i < ast.len() {
-        match ast[i] {
-            Ir::Add(value)         => mem.add(value),
-            Ir::Sub(value)         => mem.subtract(value),
-            Ir::Shift(dir, steps)  => mem.shift(dir, steps),
-            Ir::Read               => mem.set(read_char() as u8),
-            Ir::Write              => write_char(mem.get() as char),
+/// Cause of a `RuntimeError`.
+#[derive(Debug)]
+pub enum ErrorKind {
+    /// An operation on `Mem` failed.
+    Mem(MemError),
+    /// Reading input or writing output failed.
+    Io(io::Error),
+    /// The program ran for more than the maximum number of steps.
+    StepLimit(u64),
+    /// The program ran for longer than the timeout.
+    Timeout(Duration),
+    /// The program read more than the maximum number of bytes of input.
+    InputLimit(usize),
+    /// The program wrote more than the maximum number of bytes of output.
+    OutputLimit(usize),
+}
 
-            // loops
-            Ir::Open => {
-                if mem.get() != 0 {
-                    stack.push(i);
-                } else {
-                    // skip to end of loop
-                    let mut unmatched = 1u32;
-                    while unmatched > 0 && i < ast.len() {
-                        i += 1;
-                        match ast[i] {
-                            Ir::Open  => unmatched += 1,
-                            Ir::Close => unmatched -= 1,
-                            _         => {}, // skip all other tokens
-                        }
-                    }
-
-                    // unmatched open
-                    if unmatched > 0 {
-                        panic!("syntax error: malformed loop!");
-                    }
+impl ErrorKind {
+    /// Returns true if the error is a limit of `Limits` being exceeded,
+    /// rather than a fault of the program.
+    pub fn is_limit(&self) -> bool {
+        matches!(*self, ErrorKind::StepLimit(_) | ErrorKind::Timeout(_) |
+                        ErrorKind::InputLimit(_) | ErrorKind::OutputLimit(_))
+    }
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ErrorKind::Mem(ref e) => write!(f, "{}", e),
+            ErrorKind::Io(ref e)  => write!(f, "{}", e),
+            ErrorKind::StepLimit(max) => {
+                write!(f, "step limit of {} exceeded", max)
+            },
+            ErrorKind::Timeout(timeout) => {
+                write!(f, "timed out after {:?}", timeout)
+            },
+            ErrorKind::InputLimit(max) => {
+                write!(f, "input limit of {} bytes exceeded", max)
+            },
+            ErrorKind::OutputLimit(max) => {
+                write!(f, "output limit of {} bytes exceeded", max)
+            },
+        }
+    }
+}
+
+/// Error that stops the evaluation of a program.
+#[derive(Debug)]
+pub struct RuntimeError {
+    /// What went wrong.
+    pub error: ErrorKind,
+    /// Span of the instruction that caused the error.
+    pub span: Span,
+}
+
+impl fmt::Display for RuntimeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.span, self.error)
+    }
+}
+
+impl std::error::Error for RuntimeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self.error {
+            ErrorKind::Mem(ref e) => Some(e),
+            ErrorKind::Io(ref e)  => Some(e),
+            _                     => None,
+        }
+    }
+}
+
+/// Number of instructions `Interpreter::run_with` executes between writing
+/// the output produced so far.
+const RUN_WITH_STEPS: usize = 65_536;
+
+/// Limits on a run of an `Interpreter`, after which it fails with a
+/// `RuntimeError` at the instruction it had reached. The step limit and
+/// timeout are checked by `run_with`, while the input and output limits are
+/// checked by every `,` and `.`.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct Limits {
+    /// Largest number of instructions to execute.
+    pub max_steps: Option<u64>,
+    /// Longest time to run for. Time spent waiting for input counts, but a
+    /// run can not be stopped while it is blocked reading.
+    pub timeout: Option<Duration>,
+    /// Largest number of bytes of input to read.
+    pub max_input: Option<usize>,
+    /// Largest number of bytes of output to write.
+    pub max_output: Option<usize>,
+}
+
+/// Why an `Interpreter` stopped running.
+#[derive(Debug)]
+pub enum Status {
+    /// The program ran to completion.
+    Halted,
+    /// The program is at a `,` and every byte of input has been read, more
+    /// must be fed with `feed_input`, or input closed with `close_input`.
+    NeedsInput,
+    /// The program ran for the number of steps it was given.
+    OutOfFuel,
+    /// The program failed, it stays at the failing instruction.
+    Error(RuntimeError),
+}
+
+/// Interpreter of a program, which holds the program lowered to `Bytecode`,
+/// so that entering or leaving a loop is a single jump, along with the tape,
+/// program counter, pending input and output of a run. The program can be run
+/// a step at a time and resumed after it stops, so it can be paused for
+/// input, time sliced or inspected while it runs.
+pub struct Interpreter {
+    code: Bytecode,
+    mem: Mem,
+    pc: usize,                // index of the next instruction
+    steps: u64,               // number of instructions executed
+    limits: Limits,           // limits of the run
+    encoding: Encoding,
+    input: VecDeque<u8>,      // bytes fed but not yet read
+    input_closed: bool,       // true once no more input will be fed
+    input_read: usize,        // number of bytes of input read
+    output: Vec<u8>,          // bytes written but not yet taken
+    output_written: usize,    // number of bytes of output written
+    counts: Option<Vec<u64>>, // executions of each instruction, if profiled
+
+    // dumps not yet taken, each with the length of the output when it was
+    // made, so they can be interleaved with it
+    dumps: Vec<(usize, String)>,
+}
+
+impl Interpreter {
+    /// Create a new `Interpreter` of `ast`, which runs on a tape created from
+    /// `config` and reads and writes cells with `encoding`.
+    pub fn new(ast: &Ast, config: &Config, encoding: Encoding) -> Interpreter {
+        Interpreter::with_mem(ast, Mem::new(config), encoding)
+    }
+
+    /// Create a new `Interpreter` of `ast`, which runs on the existing tape
+    /// `mem`.
+    pub fn with_mem(ast: &Ast, mem: Mem, encoding: Encoding) -> Interpreter {
+        Interpreter {
+            code: lower(ast),
+            mem,
+            pc: 0,
+            steps: 0,
+            limits: Default::default(),
+            encoding,
+            input: VecDeque::new(),
+            input_closed: false,
+            input_read: 0,
+            output: Vec::new(),
+            output_written: 0,
+            counts: None,
+            dumps: Vec::new(),
+        }
+    }
+
+    /// Replaces the program with `ast`, starting at its first instruction,
+    /// while keeping the tape and any input not yet read.
+    pub fn load(&mut self, ast: &Ast) {
+        self.code = lower(ast);
+        self.pc = 0;
+        if self.counts.is_some() {
+            self.enable_profile();
+        }
+    }
+
+    /// Returns the `Bytecode` of the program.
+    pub fn code(&self) -> &Bytecode {
+        &self.code
+    }
+
+    /// Starts counting the number of times each instruction is executed.
+    pub fn enable_profile(&mut self) {
+        self.counts = Some(vec![0; self.code.len()]);
+    }
+
+    /// Returns the number of times each instruction was executed, indexed
+    /// like the `Bytecode`, or `None` unless `enable_profile` was called.
+    pub fn profile(&self) -> Option<&[u64]> {
+        self.counts.as_deref()
+    }
+
+    /// Returns the tape.
+    pub fn mem(&self) -> &Mem {
+        &self.mem
+    }
+
+    /// Returns the tape mutably, so cells can be changed between steps.
+    pub fn mem_mut(&mut self) -> &mut Mem {
+        &mut self.mem
+    }
+
+    /// Consumes the `Interpreter`, returning the tape.
+    pub fn into_mem(self) -> Mem {
+        self.mem
+    }
+
+    /// Returns the index in the `Bytecode` of the next instruction, which is
+    /// its length once the program has halted.
+    pub fn pc(&self) -> usize {
+        self.pc
+    }
+
+    /// Returns the number of instructions executed so far.
+    pub fn steps(&self) -> u64 {
+        self.steps
+    }
+
+    /// Sets the `Limits` of the run.
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.limits = limits;
+    }
+
+    /// Returns the span of the next instruction, or of the last one once the
+    /// program has halted. Returns `None` for an empty program.
+    pub fn span(&self) -> Option<Span> {
+        self.code.get(self.pc).or(self.code.last()).map(|instr| instr.span)
+    }
+
+    /// Returns true once the program has halted.
+    pub fn is_halted(&self) -> bool {
+        self.pc >= self.code.len()
+    }
+
+    /// Adds `bytes` to the end of the input.
+    pub fn feed_input(&mut self, bytes: &[u8]) {
+        self.input.extend(bytes);
+    }
+
+    /// Marks the end of input, after which `,` stores the value given by the
+    /// EOF policy of the tape instead of waiting for more.
+    pub fn close_input(&mut self) {
+        self.input_closed = true;
+    }
+
+    /// Returns the output written since it was last taken.
+    pub fn take_output(&mut self) -> Vec<u8> {
+        for dump in self.dumps.iter_mut() {
+            dump.0 = 0;
+        }
+        mem::take(&mut self.output)
+    }
+
+    /// Returns the dumps made by `Op::Dump` since they were last taken, each
+    /// a line without the newline.
+    pub fn take_dumps(&mut self) -> Vec<String> {
+        mem::take(&mut self.dumps).into_iter().map(|dump| dump.1).collect()
+    }
+
+    /// Writes the output and the dumps made since they were last taken to
+    /// `output`, in the order they were made, with each dump on a line of its
+    /// own. Ends the output with a newline if it stops part way through a
+    /// line, so whatever is written next starts on a line of its own too.
+    pub fn write_pending_lines<W: Write>(&mut self, output: &mut W)
+                                         -> io::Result<()> {
+        let last = self.dumps.last().map_or(0, |dump| dump.0);
+        let open = self.output.len() > last
+            && self.output.last() != Some(&b'\n');
+        self.write_pending_with(output, |output, dump, open| {
+            if open {
+                writeln!(output)?;
+            }
+            writeln!(output, "{}", dump)
+        })?;
+        if open {
+            writeln!(output)?;
+        }
+        Ok(())
+    }
+
+    /// Writes the output to `output` and the dumps to `stderr`, in the order
+    /// they were made.
+    fn write_pending<W: Write>(&mut self, output: &mut W) -> io::Result<()> {
+        self.write_pending_with(output, |output, dump, _| {
+            output.flush()?;
+            writeln!(io::stderr(), "{}", dump)
+        })
+    }
+
+    /// Writes the output to `output`, passing each dump to `write_dump` at the
+    /// point of the output it was made, along with whether the output written
+    /// since the previous dump stops part way through a line.
+    fn write_pending_with<W, F>(&mut self, output: &mut W, mut write_dump: F)
+                                -> io::Result<()>
+        where W: Write, F: FnMut(&mut W, &str, bool) -> io::Result<()> {
+        let written = mem::take(&mut self.output);
+        let mut start = 0;
+        for (end, dump) in mem::take(&mut self.dumps) {
+            output.write_all(&written[start..end])?;
+            let open = end > start && written[end - 1] != b'\n';
+            write_dump(output, &dump, open)?;
+            start = end;
+        }
+        output.write_all(&written[start..])
+    }
+
+    /// Returns a `RuntimeError` raised by the next instruction, or at the
+    /// start of the source if the program is empty.
+    fn error(&self, error: ErrorKind) -> RuntimeError {
+        let start = Position { offset: 0, line: 1, col: 1 };
+        RuntimeError {
+            error,
+            span: self.span().unwrap_or(Span::at(start)),
+        }
+    }
+
+    /// Reads a cell of input into the current cell, or applies the EOF policy
+    /// if input has ended. Returns false if it must wait for more input.
+    fn read_input(&mut self) -> Result<bool, ErrorKind> {
+        // the bytes of the next cell count against the limit before it is
+        // read, an invalid first byte is an error once it is read
+        let width = match self.input.front() {
+            Some(&first) if self.encoding == Encoding::Utf8 => {
+                utf8_width(first).unwrap_or(1)
+            },
+            Some(_) => 1,
+            None    => 0,
+        };
+        if let Some(max) = self.limits.max_input {
+            if self.input_read + width > max {
+                return Err(ErrorKind::InputLimit(max));
+            }
+        }
+
+        match read(&mut self.input, self.encoding) {
+            Ok(Some(value)) => {
+                self.input_read += width;
+                self.mem.input(Some(value));
+                Ok(true)
+            },
+            Ok(None) if !self.input_closed => Ok(false),
+            Ok(None) if self.input.is_empty() => {
+                self.mem.input(None);
+                Ok(true)
+            },
+            Ok(None) => Err(ErrorKind::Io(invalid_utf8())),
+            Err(e)   => Err(ErrorKind::Io(e)),
+        }
+    }
+
+    /// Writes the current cell to the output.
+    fn write_output(&mut self) -> Result<(), ErrorKind> {
+        let start = self.output.len();
+        write(&mut self.output, self.encoding, self.mem.get());
+        let width = self.output.len() - start;
+        if let Some(max) = self.limits.max_output {
+            if self.output_written + width > max {
+                self.output.truncate(start);
+                return Err(ErrorKind::OutputLimit(max));
+            }
+        }
+        self.output_written += width;
+        Ok(())
+    }
+
+    /// Dumps the pointer and the cells around it, with the current cell in
+    /// brackets.
+    fn dump(&mut self) {
+        let dump = format!("{}: pointer at cell {}: {}",
+                           self.code[self.pc].span, self.mem.position(),
+                           self.mem.window(DUMP_WINDOW));
+        self.dumps.push((self.output.len(), dump));
+    }
+
+    /// Executes the next instruction, or returns the `Status` the program
+    /// stopped with instead.
+    #[inline(always)]
+    fn execute(&mut self) -> Result<(), Status> {
+        let pc = self.pc;
+        let op = match self.code.get(pc) {
+            Some(instr) => instr.op,
+            None        => return Err(Status::Halted),
+        };
+        let mut next = pc + 1;
+        let mem = &mut self.mem;
+        let result = match op {
+            Op::Add(value) => mem.add(value).map_err(ErrorKind::Mem),
+            Op::Sub(value) => mem.subtract(value).map_err(ErrorKind::Mem),
+            Op::Shift(dir, steps) => {
+                mem.shift(dir, steps).map_err(ErrorKind::Mem)
+            },
+
+            // wait at the ',' until it has a whole cell of input to read, or
+            // input has ended
+            Op::Read => match self.read_input() {
+                Ok(true)  => Ok(()),
+                Ok(false) => return Err(Status::NeedsInput),
+                Err(e)    => Err(e),
+            },
+            Op::Write => self.write_output(),
+            Op::Dump => {
+                self.dump();
+                Ok(())
+            },
+
+            // loops, the jump targets are the matching bracket so the loop
+            // continues from the instruction after it
+            Op::JumpIfZero(target) => {
+                if mem.get() == 0 {
+                    next = target + 1;
                 }
+                Ok(())
             },
-            Ir::Close => {
-                let open_index = match stack.pop() {
-                    Some(index) => index,
-                    None        => panic!("syntax error: malformed loop!"),
-                };
+            Op::JumpIfNonZero(target) => {
                 if mem.get() != 0 {
-                    // move i to the open index and then the loop will increment
-                    // to the next instruction
-                    i = open_index;
-                    stack.push(open_index);
+                    next = target + 1;
                 }
+                Ok(())
             },
 
             // optimizations
-            Ir::Clear                   => mem.clear(),
-            Ir::Scan(dir)               => mem.scan(dir),
-            Ir::Copy(dir, steps)        => mem.copy(dir, steps),
-            Ir::Mul(dir, steps, factor) => mem.multiply(dir, steps, factor),
+            Op::Clear => {
+                mem.clear();
+                Ok(())
+            },
+            Op::Scan(dir) => match mem.scan(dir) {
+                Ok(true)  => Ok(()),
+
+                // no zero cell will be found, so stay at the scan, which
+                // takes a step each time like the loop it replaced, until a
+                // limit stops it
+                Ok(false) => {
+                    next = pc;
+                    Ok(())
+                },
+                Err(e) => Err(ErrorKind::Mem(e)),
+            },
+            Op::Copy(dir, steps) => {
+                mem.copy(dir, steps).map_err(ErrorKind::Mem)
+            },
+            Op::Mul(dir, steps, factor) => {
+                mem.multiply(dir, steps, factor).map_err(ErrorKind::Mem)
+            },
+        };
+
+        if let Err(error) = result {
+            return Err(Status::Error(self.error(error)));
+        }
+
+        if let Some(counts) = self.counts.as_mut() {
+            counts[pc] += 1;
+        }
+        self.pc = next;
+        Ok(())
+    }
+
+    /// Executes a single instruction, returning `OutOfFuel` if the program
+    /// has more to run.
+    pub fn step(&mut self) -> Status {
+        self.run_for(1)
+    }
+
+    /// Runs the program for at most `steps` instructions.
+    pub fn run_for(&mut self, steps: usize) -> Status {
+        // count in a local, which is added to the total once, as updating it
+        // every step slows down the hot loop
+        let mut executed = 0;
+        while executed < steps {
+            if let Err(status) = self.execute() {
+                self.steps += executed as u64;
+                return status;
+            }
+            executed += 1;
+        }
+        self.steps += executed as u64;
+        if self.is_halted() {
+            Status::Halted
+        } else {
+            Status::OutOfFuel
+        }
+    }
+
+    /// Runs the program until it halts, needs input or fails.
+    pub fn run(&mut self) -> Status {
+        let mut executed = 0;
+        loop {
+            if let Err(status) = self.execute() {
+                self.steps += executed;
+                return status;
+            }
+            executed += 1;
         }
+    }
 
-        i += 1; // increment the index
+    /// Runs the program until it halts or fails, reading input from `input`
+    /// whenever it needs more and writing its output to `output` as it goes.
+    /// Fails once the program exceeds the `Limits` set with `set_limits`.
+    pub fn run_with<R, W>(&mut self, input: &mut R, output: &mut W)
+                          -> Result<(), RuntimeError>
+        where R: BufRead, W: Write {
+        let start = Instant::now();
+        loop {
+            // run in slices, so the limits are checked between them
+            let mut fuel = RUN_WITH_STEPS;
+            if let Some(max) = self.limits.max_steps {
+                if self.steps >= max && !self.is_halted() {
+                    return Err(self.error(ErrorKind::StepLimit(max)));
+                }
+                fuel = cmp::min(fuel as u64, max.saturating_sub(self.steps)) as usize;
+            }
+            if let Some(timeout) = self.limits.timeout {
+                if start.elapsed() >= timeout && !self.is_halted() {
+                    return Err(self.error(ErrorKind::Timeout(timeout)));
+                }
+            }
+
+            let status = self.run_for(fuel);
+            self.write_pending(output)
+                .map_err(|e| self.error(ErrorKind::Io(e)))?;
+
+            match status {
+                Status::Halted     => return Ok(()),
+                Status::OutOfFuel  => {},
+                Status::Error(e)   => return Err(e),
+
+                // flush the output first, as it may prompt for the input
+                Status::NeedsInput => {
+                    output.flush()
+                          .and_then(|_| self.read_from(input))
+                          .map_err(|e| self.error(ErrorKind::Io(e)))?;
+                },
+            }
+        }
     }
+
+    /// Feeds the bytes available from `input`, or closes the input if it
+    /// has ended.
+    fn read_from<R: BufRead>(&mut self, input: &mut R) -> io::Result<()> {
+        let fed = loop {
+            match input.fill_buf() {
+                Ok(buf) => {
+                    self.feed_input(buf);
+                    break buf.len();
+                },
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {},
+                Err(e) => return Err(e),
+            }
+        };
+        if fed == 0 {
+            self.close_input();
+        }
+        input.consume(fed);
+        Ok(())
+    }
+}
+
+/// Evaluates an `Ast` using `mem`, reading cells from `input` and writing them
+/// to `output` with `encoding`.
+pub fn eval_with<R, W>(ast: &Ast, mem: &mut Mem, encoding: Encoding,
+                       input: &mut R, output: &mut W)
+                       -> Result<(), RuntimeError>
+    where R: BufRead, W: Write {
+    // move the tape into the interpreter and back, leaving a single cell in
+    // its place meanwhile, rather than copying it
+    let placeholder = Mem::new(&Config { tape_size: 1, ..Default::default() });
+    let tape = std::mem::replace(mem, placeholder);
+    let mut interpreter = Interpreter::with_mem(ast, tape, encoding);
+    let result = interpreter.run_with(input, output);
+    *mem = interpreter.into_mem();
+    result
+}
+
+/// Evaluates an `Ast` like `eval_with`, using `stdin` and `stdout`.
+pub fn eval(ast: &Ast, mem: &mut Mem, encoding: Encoding)
+            -> Result<(), RuntimeError> {
+    eval_with(ast, mem, encoding, &mut io::stdin().lock(),
+              &mut io::stdout().lock())
 }
 
+/// Evaluates an `Ast` on a new `Mem` created from `config`, reading from the
+/// bytes of `input`. Returns the bytes written to output and the tape dumps
+/// made by `#`, along with the result of the evaluation, so they are kept even
+/// if it fails.
+pub fn run(ast: &Ast, config: &Config, encoding: Encoding, input: &[u8])
+           -> (Vec<u8>, Vec<String>, Result<(), RuntimeError>) {
+    let mut interpreter = Interpreter::new(ast, config, encoding);
+    interpreter.feed_input(input);
+    interpreter.close_input();
+    let result = match interpreter.run() {
+        Status::Error(e) => Err(e),
+        _                => Ok(()),
+    };
+    let dumps = interpreter.take_dumps();
+    (interpreter.take_output(), dumps, result)
+}
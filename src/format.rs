@@ -0,0 +1,109 @@
+use std::fmt;
+
+use crate::syntax::{Ast, Dir, Ir, Node, Span};
+
+/// Number of spaces each level of loop is indented by.
+const INDENT: usize = 2;
+
+/// Error of `format`, for an `Ast` which can not be written as source.
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+pub enum FormatError {
+    /// An `Ir::Copy` or `Ir::Mul`, with the span of the loop it replaced,
+    /// which can not be written without that loop.
+    Optimized(Span),
+}
+
+impl fmt::Display for FormatError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            FormatError::Optimized(span) => {
+                write!(f, "{}: can not format an optimized copy or \
+                           multiplication loop, format the Ast before \
+                           optimizing it", span)
+            },
+        }
+    }
+}
+
+impl std::error::Error for FormatError {}
+
+/// Returns the source of a single instruction, with the body of a loop, or an
+/// error for `Ir::Copy` and `Ir::Mul`.
+fn source(node: &Node) -> Result<String, FormatError> {
+    let shift = |dir| match dir {
+        Dir::Left  => "<",
+        Dir::Right => ">",
+    };
+    Ok(match node.ir {
+        Ir::Add(value)        => "+".repeat(value as usize),
+        Ir::Sub(value)        => "-".repeat(value as usize),
+        Ir::Shift(dir, steps) => shift(dir).repeat(steps),
+        Ir::Read              => ",".to_string(),
+        Ir::Write             => ".".to_string(),
+        Ir::Dump              => "#".to_string(),
+        Ir::Clear             => "[-]".to_string(),
+        Ir::Scan(dir)         => format!("[{}]", shift(dir)),
+        Ir::Loop(ref body)    => {
+            let body: String = body.iter().map(source)
+                                   .collect::<Result<_, _>>()?;
+            format!("[{}]", body)
+        },
+        Ir::Copy(..) | Ir::Mul(..) => {
+            return Err(FormatError::Optimized(node.span));
+        },
+    })
+}
+
+/// Appends the lines of `ast`, indented to `depth`, to `lines`.
+fn format_into(ast: &Ast, depth: usize, width: usize,
+               lines: &mut Vec<String>) -> Result<(), FormatError> {
+    let indent = " ".repeat(depth * INDENT);
+    let mut line = indent.clone();
+    for node in ast.iter() {
+        let text = source(node)?;
+
+        // loops which do not fit on the rest of the line are split, with the
+        // brackets on lines of their own around the indented body
+        if let Ir::Loop(ref body) = node.ir {
+            if line.len() + text.len() > width {
+                if line.len() > indent.len() {
+                    lines.push(line);
+                }
+                lines.push(format!("{}[", indent));
+                format_into(body, depth + 1, width, lines)?;
+                lines.push(format!("{}]", indent));
+                line = indent.clone();
+                continue;
+            }
+        }
+
+        // anything else is wrapped at the width
+        for c in text.chars() {
+            if line.len() >= width && line.len() > indent.len() {
+                lines.push(line);
+                line = indent.clone();
+            }
+            line.push(c);
+        }
+    }
+    if line.len() > indent.len() {
+        lines.push(line);
+    }
+    Ok(())
+}
+
+/// Formats an `Ast` as source, without comments. Instructions are wrapped to
+/// lines of at most `width` columns where they can be, and a loop which does
+/// not fit on the line it starts on has its brackets on lines of their own,
+/// around its body indented by another level. The `Ast` should be formatted
+/// before it is optimized, as it fails with a `FormatError` if it holds an
+/// `Ir::Copy` or `Ir::Mul`.
+pub fn format(ast: &Ast, width: usize) -> Result<String, FormatError> {
+    let mut lines = Vec::new();
+    format_into(ast, 0, width, &mut lines)?;
+    let mut formatted = lines.join("\n");
+    if !formatted.is_empty() {
+        formatted.push('\n');
+    }
+    Ok(formatted)
+}
@@ -0,0 +1,61 @@
+//! A Brainfuck parser, optimizer, interpreter and compiler to C and Rust.
+//!
+//! A program is parsed from a `ByteStream` into an `Ast`, optimized and then
+//! either run by an `Interpreter` or emitted as source of another language.
+//!
+//! ```
+//! use brainfuck::{parse, optimize, ByteStream, Encoding, Interpreter};
+//! use brainfuck::{OptLevel, Status};
+//!
+//! let source = b"++++++++[>++++++++<-]>+.";
+//! let ast = parse(&mut ByteStream::new(&source[..])).unwrap();
+//! let ast = optimize(OptLevel::Default, &ast);
+//!
+//! let config = Default::default();
+//! let mut interpreter = Interpreter::new(&ast, &config, Encoding::Bytes);
+//! assert!(matches!(interpreter.run(), Status::Halted));
+//! assert_eq!(interpreter.take_output(), b"A".to_vec());
+//! ```
+
+pub use byte_stream::ByteStream;
+pub use bytecode::{lower, Bytecode, Instr, Op};
+pub use debugger::{Breakpoint, Debugger};
+pub use emit::{emit_c, emit_ir, emit_rust};
+pub use error::Error;
+pub use eval::{eval, eval_with, run, Encoding, ErrorKind, Interpreter};
+pub use eval::{Limits, RuntimeError, Status};
+pub use format::{format, FormatError};
+pub use lint::{lint, Lint, Warning};
+pub use mem::{CellWidth, Config, Eof, Mem, MemError, OutOfBounds, Overflow};
+pub use mem::{TapeMode, DEFAULT_MAX_TAPE_SIZE, DEFAULT_TAPE_SIZE, DUMP_WINDOW};
+pub use optimizer::{optimize, optimize_with, OptLevel, OptimizeOptions};
+pub use parser::{parse, parse_with, ParseError, ParseOptions};
+pub use repl::Repl;
+pub use syntax::{Ast, Dir, Ir, Left, Node, Position, Right, Span};
+
+/// Buffered stream of the bytes of a source file.
+pub mod byte_stream;
+/// Flat, jump-resolved form of the `Ast` run by the interpreter.
+pub mod bytecode;
+/// Interactive debugger of programs.
+pub mod debugger;
+/// Emitters of the `Ast` as C, Rust or plain `Ir`.
+pub mod emit;
+/// Error of any stage of reading, running or emitting a program.
+pub mod error;
+/// Interpreter of programs.
+pub mod eval;
+/// Formatter of programs.
+pub mod format;
+/// Linter of programs, which finds likely mistakes.
+pub mod lint;
+/// Tape of cells and the policies for how it behaves.
+pub mod mem;
+/// Optimizations of the `Ast`.
+pub mod optimizer;
+/// Parser of source into an `Ast`.
+pub mod parser;
+/// Interactive read-eval-print loop.
+pub mod repl;
+/// Syntax of programs: `Ir`, `Ast` and source locations.
+pub mod syntax;
@@ -0,0 +1,122 @@
+use std::fmt;
+
+use crate::syntax::{Ast, Ir, Left, Right, Span};
+
+/// Kind of likely mistake found by `lint`.
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+pub enum Warning {
+    /// A loop which is never entered, because the current cell is always 0
+    /// where it starts: at the start of the program or right after another
+    /// loop.
+    DeadLoop,
+    /// A loop with an empty body, which never ends once it is entered.
+    EmptyLoop,
+    /// A run of `+` and `-`, or of `<` and `>`, which partly cancel each
+    /// other out.
+    Cancelling,
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Warning::DeadLoop   => {
+                write!(f, "loop is never entered, as the cell is always 0 \
+                           here")
+            },
+            Warning::EmptyLoop  => write!(f, "empty loop never ends"),
+            Warning::Cancelling => {
+                write!(f, "instructions cancel each other out")
+            },
+        }
+    }
+}
+
+/// A `Warning` and the `Span` of source it was found in.
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+pub struct Lint {
+    /// What was found.
+    pub warning: Warning,
+    /// Source it was found in.
+    pub span: Span,
+}
+
+impl fmt::Display for Lint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.span, self.warning)
+    }
+}
+
+/// Kind of a run of instructions which may cancel out, along with whether
+/// each of its directions has been seen.
+#[derive(Clone, Copy, Eq, PartialEq)]
+enum Run {
+    Add(bool, bool),
+    Shift(bool, bool),
+}
+
+/// Appends the lints of `ast` to `lints`. The first loop of the program is
+/// dead if `at_start`.
+fn lint_into(ast: &Ast, at_start: bool, lints: &mut Vec<Lint>) {
+    let mut run: Option<(Run, Span)> = None;
+    let mut after_loop = at_start;
+    for node in ast.iter() {
+        // extend the current run, or end it and start another
+        let next = match node.ir {
+            Ir::Add(_)          => Some(Run::Add(true, false)),
+            Ir::Sub(_)          => Some(Run::Add(false, true)),
+            Ir::Shift(Left, _)  => Some(Run::Shift(true, false)),
+            Ir::Shift(Right, _) => Some(Run::Shift(false, true)),
+            _                   => None,
+        };
+        run = match (run, next) {
+            (Some((Run::Add(a, b), span)), Some(Run::Add(c, d))) => {
+                Some((Run::Add(a || c, b || d), span.to(node.span)))
+            },
+            (Some((Run::Shift(a, b), span)), Some(Run::Shift(c, d))) => {
+                Some((Run::Shift(a || c, b || d), span.to(node.span)))
+            },
+            (previous, next) => {
+                end_run(previous, lints);
+                next.map(|next| (next, node.span))
+            },
+        };
+
+        match node.ir {
+            Ir::Loop(ref body) => {
+                if after_loop {
+                    lints.push(Lint { warning: Warning::DeadLoop,
+                                      span: node.span });
+                } else if body.is_empty() {
+                    lints.push(Lint { warning: Warning::EmptyLoop,
+                                      span: node.span });
+                }
+                lint_into(body, false, lints);
+                after_loop = true;
+            },
+            Ir::Clear | Ir::Scan(_) => after_loop = true,
+            Ir::Write | Ir::Dump    => {}, // the cell is unchanged
+            _                       => after_loop = false,
+        }
+    }
+    end_run(run, lints);
+}
+
+/// Appends a lint for a finished `run` if it went both ways.
+fn end_run(run: Option<(Run, Span)>, lints: &mut Vec<Lint>) {
+    match run {
+        Some((Run::Add(true, true), span))   |
+        Some((Run::Shift(true, true), span)) => {
+            lints.push(Lint { warning: Warning::Cancelling, span });
+        },
+        _ => {},
+    }
+}
+
+/// Returns the likely mistakes in an `Ast` as parsed, in the order they
+/// appear in the source.
+pub fn lint(ast: &Ast) -> Vec<Lint> {
+    let mut lints = Vec::new();
+    lint_into(ast, true, &mut lints);
+    lints.sort_by_key(|lint| lint.span.start.offset);
+    lints
+}
@@ -1,132 +1,857 @@
 // Brainfuck interpreter written in Rust.
 
-// TODO: remove once 1.0-final lands
-#![feature(box_syntax)]
-#![feature(collections)]
-#![feature(core)]
-#![feature(int_uint)]
-#![feature(old_io)]
-#![feature(old_path)]
-#![feature(os)]
+use getopts::{Fail, Matches, Options};
 
-extern crate getopts;
+use std::cmp;
+use std::env;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::process;
+use std::time::{Duration, Instant};
 
-use getopts::Options;
+use brainfuck::{emit_c, emit_ir, emit_rust, format, optimize_with, parse_with};
+use brainfuck::{Ast, ByteStream, CellWidth, Config, Debugger, Encoding, Eof};
+use brainfuck::{Interpreter, Limits, OptLevel, OutOfBounds, Overflow};
+use brainfuck::{Error, ErrorKind, ParseOptions, Repl, RuntimeError, TapeMode};
+use brainfuck::{OptimizeOptions, DEFAULT_MAX_TAPE_SIZE};
 
-use std::old_io::File;
+static VERSION: &str = "0.1.0";
 
-use byte_stream::ByteStream;
-use emit::{emit_c, emit_ir, emit_rust};
-use eval::eval;
-use optimizer::{optimize, OptLevel};
-use parser::parse;
+/// Exit status when `lint` finds likely mistakes.
+const EXIT_LINT: i32 = 1;
+/// Exit status when the command line is invalid, such as an unknown command,
+/// option or value.
+const EXIT_USAGE: i32 = 2;
+/// Exit status when the source fails to parse.
+const EXIT_PARSE: i32 = 3;
+/// Exit status when the program fails while it runs.
+const EXIT_RUNTIME: i32 = 4;
+/// Exit status when reading or writing a file or stream fails.
+const EXIT_IO: i32 = 5;
+/// Exit status when a run is stopped by `--max-steps`, `--timeout`,
+/// `--max-input` or `--max-output`.
+const EXIT_LIMIT: i32 = 6;
 
-mod byte_stream;
-mod emit;
-mod eval;
-mod mem;
-mod optimizer;
-mod parser;
-mod syntax;
+/// Option of a command, with its short and long names, the description shown
+/// in its help and the hint of its argument, which is empty for a flag.
+struct Opt {
+    short: &'static str,
+    long: &'static str,
+    desc: &'static str,
+    hint: &'static str,
+}
+
+/// Creates an `Opt` which takes an argument.
+const fn opt(short: &'static str, long: &'static str, desc: &'static str,
+             hint: &'static str) -> Opt {
+    Opt { short, long, desc, hint }
+}
+
+/// Creates an `Opt` which is a flag.
+const fn flag(long: &'static str, desc: &'static str) -> Opt {
+    Opt { short: "", long, desc, hint: "" }
+}
+
+static SOURCE_OPTS: &[Opt] = &[
+    opt("e", "", "Use the program CODE instead of a file", "CODE"),
+];
+
+static PARSE_OPTS: &[Opt] = &[
+    flag("dump", "Parse '#' as an instruction which dumps the pointer and \
+                  nearby cells to stderr"),
+    flag("separator", "Stop the program at the first '!', with the rest of \
+                       the source as its input"),
+];
+
+static OPTIMIZE_OPTS: &[Opt] = &[
+    opt("O", "opt-level", "Optimize with possible levels 0-3, default 2",
+        "LEVEL"),
+];
+
+static TAPE_OPTS: &[Opt] = &[
+    opt("", "tape-size", "Number of cells in the tape, default 65536",
+        "CELLS"),
+    opt("", "cell-width", "Number of bits in each cell, one of 8, 16, 32 or \
+                           64, default 8", "BITS"),
+    opt("", "overflow", "What happens when a cell overflows, default wrap",
+        "[wrap|saturate|error]"),
+    opt("", "eof", "What ',' stores at the end of input, default unchanged",
+        "[zero|minus-one|unchanged]"),
+];
+
+// the emitted programs have a fixed tape and do not check its ends, so emit
+// does not take these
+static BOUNDS_OPTS: &[Opt] = &[
+    flag("grow", "Grow the tape to the right when the pointer moves past its \
+                  end"),
+    flag("grow-left", "Also grow the tape to the left, implies --grow"),
+    opt("", "max-tape-size", "Number of cells a growing tape may hold, \
+                              default 16777216", "CELLS"),
+    opt("", "out-of-bounds", "What happens when the pointer moves off the \
+                              tape, default error", "[error|wrap|clamp]"),
+];
+
+static IO_OPTS: &[Opt] = &[
+    flag("utf8", "Read and write cells as UTF-8 encoded code points instead \
+                  of raw bytes"),
+    opt("", "input", "Read the input of the program from FILE instead of \
+                      stdin", "FILE"),
+    opt("", "input-string", "Use STR as the input of the program instead of \
+                             stdin", "STR"),
+];
 
-static VERSION: &'static str = "0.1.0";
+static LIMIT_OPTS: &[Opt] = &[
+    opt("", "max-steps", "Stop with exit status 6 after executing this many \
+                          instructions", "STEPS"),
+    opt("", "timeout", "Stop with exit status 6 after running for this many \
+                        seconds", "SECS"),
+    opt("", "max-input", "Stop with exit status 6 instead of reading more \
+                          than this many bytes of input", "BYTES"),
+    opt("", "max-output", "Stop with exit status 6 instead of writing more \
+                           than this many bytes of output", "BYTES"),
+];
+
+static EMIT_OPTS: &[Opt] = &[
+    opt("t", "target", "Comma separated list of the types of files to emit",
+        "[c|ir|rust]"),
+];
+
+static FMT_OPTS: &[Opt] = &[
+    opt("w", "width", "Number of columns to wrap lines at, default 80",
+        "COLS"),
+];
+
+static PROFILE_OPTS: &[Opt] = &[
+    opt("n", "top", "Number of instructions to report, default 10", "N"),
+];
+
+static BENCH_OPTS: &[Opt] = &[
+    opt("n", "runs", "Number of times to run the program, default 10", "N"),
+];
+
+/// Subcommand of `bfi`, with the arguments, summary and description shown in
+/// its help, the groups of options it takes and the function which runs it.
+struct Command {
+    name: &'static str,
+    args: &'static str,
+    summary: &'static str,
+    about: &'static str,
+    opts: &'static [&'static [Opt]],
+    main: fn(&Matches),
+}
+
+static COMMANDS: &[Command] = &[
+    Command {
+        name: "run",
+        args: "[FILE | -e CODE | -]",
+        summary: "Run a program, or start a REPL without one",
+        about: "Runs the program in FILE, the program CODE given with -e, or \
+                the program read from\nstdin given -. Without a program, \
+                starts a REPL which runs each line as it is\nentered.",
+        opts: &[SOURCE_OPTS, PARSE_OPTS, OPTIMIZE_OPTS, TAPE_OPTS,
+                BOUNDS_OPTS, IO_OPTS, LIMIT_OPTS],
+        main: run,
+    },
+    Command {
+        name: "emit",
+        args: "FILE",
+        summary: "Compile a program to C, Rust or Ir",
+        about: "Compiles the program in FILE to a file of each target, named \
+                after FILE.",
+        opts: &[EMIT_OPTS, PARSE_OPTS, OPTIMIZE_OPTS, TAPE_OPTS],
+        main: emit,
+    },
+    Command {
+        name: "fmt",
+        args: "(FILE | -e CODE | -)",
+        summary: "Print a program formatted",
+        about: "Prints the program formatted, without comments, to stdout.",
+        opts: &[FMT_OPTS, SOURCE_OPTS, PARSE_OPTS],
+        main: fmt,
+    },
+    Command {
+        name: "lint",
+        args: "(FILE | -e CODE | -)",
+        summary: "Warn of likely mistakes in a program",
+        about: "Warns of likely mistakes in the program, exiting with status \
+                1 if there are any.",
+        opts: &[SOURCE_OPTS, PARSE_OPTS],
+        main: lint,
+    },
+    Command {
+        name: "debug",
+        args: "(FILE | -e CODE)",
+        summary: "Debug a program interactively",
+        about: "Runs the program in an interactive debugger, which reads \
+                commands from stdin.",
+        opts: &[SOURCE_OPTS, PARSE_OPTS, OPTIMIZE_OPTS, TAPE_OPTS,
+                BOUNDS_OPTS, IO_OPTS],
+        main: debug,
+    },
+    Command {
+        name: "profile",
+        args: "(FILE | -e CODE | -)",
+        summary: "Run a program and report where it spent its time",
+        about: "Runs the program, then reports the instructions it executed \
+                most to stderr.",
+        opts: &[PROFILE_OPTS, SOURCE_OPTS, PARSE_OPTS, OPTIMIZE_OPTS,
+                TAPE_OPTS, BOUNDS_OPTS, IO_OPTS, LIMIT_OPTS],
+        main: profile,
+    },
+    Command {
+        name: "bench",
+        args: "(FILE | -e CODE | -)",
+        summary: "Time a number of runs of a program",
+        about: "Times a number of runs of the program, discarding its \
+                output. The program only\nreads input given with --separator, \
+                --input or --input-string.",
+        opts: &[BENCH_OPTS, SOURCE_OPTS, PARSE_OPTS, OPTIMIZE_OPTS,
+                TAPE_OPTS, BOUNDS_OPTS, IO_OPTS, LIMIT_OPTS],
+        main: bench,
+    },
+];
+
+/// Prints the help message listing the commands to stdout.
+fn help(program: &str) -> io::Result<()> {
+    let mut out = io::stdout().lock();
+    writeln!(out, "Usage: {} [COMMAND] [options] [ARGS]\n", program)?;
+    writeln!(out, "Commands:")?;
+    for command in COMMANDS.iter() {
+        writeln!(out, "    {:<8} {}", command.name, command.summary)?;
+    }
+    writeln!(out, "    help     Print the help of a command")?;
+    writeln!(out, "\nWithout a command, starts a REPL. Run '{} help COMMAND' \
+                   for the options of a command.", program)?;
+    writeln!(out, "\nExit status:")?;
+    for (status, meaning) in [
+        (0,            "success"),
+        (EXIT_LINT,    "lint found likely mistakes"),
+        (EXIT_USAGE,   "the command line is invalid"),
+        (EXIT_PARSE,   "the source failed to parse"),
+        (EXIT_RUNTIME, "the program failed while it ran"),
+        (EXIT_IO,      "reading or writing a file or stream failed"),
+        (EXIT_LIMIT,   "the run was stopped by a limit"),
+    ] {
+        writeln!(out, "    {}  {}", status, meaning)?;
+    }
+    Ok(())
+}
 
-/// Prints the help message to stdout.
-fn help(program: &str, opts: Options) {
-    let brief = format!("Usage: {} [options] FILE", program);
-    println!("{}", opts.usage(&brief));
+/// Prints the help message of a `command` to stdout.
+fn command_help(program: &str, command: &Command) -> io::Result<()> {
+    let brief = format!("Usage: {} {} [options] {}\n\n{}", program,
+                        command.name, command.args, command.about);
+    writeln!(io::stdout().lock(), "{}", options(command).usage(&brief))
 }
 
 /// Prints the version information to stdout.
-fn version(program: &str) {
-    println!("{} {}", program, VERSION);
+fn version(program: &str) -> io::Result<()> {
+    writeln!(io::stdout().lock(), "{} {}", program, VERSION)
 }
 
-/// Main function.
-fn main() {
-    let args = std::os::args();
-    let program = &args[0].clone();
+/// Exits with an error if writing to stdout failed, as it does once a pipe
+/// it writes to is closed.
+fn check_stdout(result: io::Result<()>) {
+    if let Err(e) = result {
+        fail("<stdout>", e.into());
+    }
+}
+
+/// Prints an error in the command line to stderr and exits.
+fn usage_error(message: &str) -> ! {
+    eprintln!("error: {}", message);
+    process::exit(EXIT_USAGE);
+}
+
+/// Returns the exit status for `error`.
+fn exit_status(error: &Error) -> i32 {
+    match *error {
+        Error::Parse(_)       => EXIT_PARSE,
+        Error::Runtime(ref e) => match e.error {
+            ErrorKind::Io(_)            => EXIT_IO,
+            ref kind if kind.is_limit() => EXIT_LIMIT,
+            _                           => EXIT_RUNTIME,
+        },
+        Error::Io(_)          => EXIT_IO,
+    }
+}
+
+/// Prints `error`, raised by the source or file `name`, to stderr and exits
+/// with the status for it.
+fn fail(name: &str, error: Error) -> ! {
+    match error {
+        Error::Io(ref e) => eprintln!("error: {}: {}", name, e),
+        ref error        => eprintln!("error: {}:{}", name, error),
+    }
+    process::exit(exit_status(&error));
+}
+
+/// Returns the number of single character edits, or swaps of adjacent
+/// characters, needed to turn `a` into `b`.
+fn distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+
+    // rows of the distances from the prefixes of `a` to those of `b`, from
+    // two rows before to the current row
+    let mut before: Vec<usize> = Vec::new();
+    let mut last: Vec<usize> = (0..=b.len()).collect();
+    for i in 1..=a.len() {
+        let mut row = vec![i; b.len() + 1];
+        for j in 1..=b.len() {
+            let cost = (a[i - 1] != b[j - 1]) as usize;
+            row[j] = cmp::min(last[j - 1] + cost,
+                              cmp::min(last[j], row[j - 1]) + 1);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                row[j] = cmp::min(row[j], before[j - 2] + 1);
+            }
+        }
+        before = last;
+        last = row;
+    }
+    last[b.len()]
+}
 
-    // command line options
+/// Returns the one of `candidates` closest to `value`, if it is close enough
+/// to be what was meant, which rules out replacing it entirely.
+fn suggest<'a, I>(value: &str, candidates: I) -> Option<&'a str>
+    where I: IntoIterator<Item = &'a str> {
+    candidates.into_iter()
+              .map(|candidate| (distance(value, candidate), candidate))
+              .filter(|&(d, candidate)| {
+                  d <= cmp::max(1, value.len() / 3) && d < candidate.len()
+              })
+              .min_by_key(|&(d, _)| d)
+              .map(|(_, candidate)| candidate)
+}
+
+/// Returns the value paired with `value` in `choices`, or exits with an error
+/// that it is an invalid `what`, suggesting the closest choice.
+fn choice<T: Copy>(what: &str, value: &str, choices: &[(&str, T)]) -> T {
+    if let Some(&(_, chosen)) = choices.iter().find(|c| c.0 == value) {
+        return chosen;
+    }
+    let names: Vec<&str> = choices.iter().map(|c| c.0).collect();
+    match suggest(value, names.iter().cloned()) {
+        Some(name) => {
+            usage_error(&format!("invalid {} '{}', did you mean '{}'?", what,
+                                 value, name));
+        },
+        None => {
+            usage_error(&format!("invalid {} '{}', expected one of {}", what,
+                                 value, names.join(", ")));
+        },
+    }
+}
+
+/// Returns the value of the option `name` parsed as a number, or `None` if it
+/// was not given. Exits with an error that it is an invalid `what` if it does
+/// not parse or is not `valid`.
+fn number<T, F>(matches: &Matches, name: &str, what: &str, valid: F)
+                -> Option<T>
+    where T: std::str::FromStr, F: Fn(&T) -> bool {
+    matches.opt_str(name).map(|s| match s.parse::<T>() {
+        Ok(n) if valid(&n) => n,
+        _ => usage_error(&format!("invalid {} '{}'", what, s)),
+    })
+}
+
+/// Returns the `Options` of a `command`.
+fn options(command: &Command) -> Options {
     let mut opts = Options::new();
     opts.optflag("h", "help", "Print this help message");
-    opts.optflag("v", "version", "Output version information and exit");
-    opts.optopt("", "emit", "Comma separated list of types of output for the \
-                             interpreter to emit.", "[c|ir|rust]");
-    opts.optopt("O", "opt-level", "Optimize with possible levels 0-3, default \
-                                 2", "LEVEL");
-
-    let matches = match opts.parse(args.tail()) {
-        Ok(m)  => m,
-        Err(e) => panic!("{}", e),
-    };
+    for opt in command.opts.iter().flat_map(|group| group.iter()) {
+        if opt.hint.is_empty() {
+            opts.optflag(opt.short, opt.long, opt.desc);
+        } else {
+            opts.optopt(opt.short, opt.long, opt.desc, opt.hint);
+        }
+    }
+    opts
+}
 
-    // help
-    if matches.opt_present("h") {
-        help(program, opts);
-        return;
+/// Parses the `args` of a `command`, exiting with an error suggesting the
+/// closest option if one is unknown.
+fn parse_args(command: &Command, args: &[String]) -> Matches {
+    match options(command).parse(args) {
+        Ok(matches) => matches,
+        Err(Fail::UnrecognizedOption(name)) => {
+            let longs = command.opts.iter()
+                                    .flat_map(|group| group.iter())
+                                    .map(|opt| opt.long)
+                                    .filter(|long| !long.is_empty());
+            let message = format!("unknown option '{}' for '{}'", name,
+                                  command.name);
+            match suggest(&name, longs) {
+                Some(long) => {
+                    usage_error(&format!("{}, did you mean '--{}'?", message,
+                                         long));
+                },
+                None => usage_error(&message),
+            }
+        },
+        Err(e) => usage_error(&format!("{}", e)),
     }
+}
 
-    // version
-    if matches.opt_present("v") {
-        version(program);
-        return;
+/// Exits with an error that there is no command `name`, suggesting the
+/// closest one.
+fn unknown_command(name: &str) -> ! {
+    let names = COMMANDS.iter().map(|c| c.name).chain(["help"]);
+    match suggest(name, names) {
+        Some(command) => {
+            usage_error(&format!("unknown command '{}', did you mean '{}'?",
+                                 name, command));
+        },
+        None => {
+            usage_error(&format!("unknown command '{}', run 'bfi help' for a \
+                                  list of commands", name));
+        },
     }
+}
+
+/// Source of a program, along with its file name, which is only a name for
+/// messages unless `from_file`.
+struct Source {
+    name: String,
+    bytes: Vec<u8>,
+    from_file: bool,
+}
+
+/// Returns true if a program was given, with `-e` or as an argument.
+fn has_source(matches: &Matches) -> bool {
+    (matches.opt_defined("e") && matches.opt_present("e")) ||
+        !matches.free.is_empty()
+}
 
-    // emit targets
-    let emit_str = match matches.opt_str("emit") {
-        Some(s) => s.clone(),
-        None    => String::new(),
+/// Returns the source of the program given with `-e`, read from stdin given
+/// `-` or read from the file named by the argument, exiting with an error if
+/// there is none.
+fn read_source(matches: &Matches) -> Source {
+    if matches.free.len() > 1 {
+        usage_error(&format!("unexpected argument '{}'", matches.free[1]));
+    }
+    let code = if matches.opt_defined("e") {
+        matches.opt_str("e")
+    } else {
+        None
     };
-    let mut emit_targets = Vec::new();
-    for target in emit_str.split(",") {
-        match target {
-            "c"    |
-            "ir"   |
-            "rust" => { emit_targets.push(target); },
-            _      => {}, // ignore invalid targets
+
+    if let Some(code) = code {
+        if let Some(name) = matches.free.first() {
+            usage_error(&format!("unexpected argument '{}' after -e", name));
+        }
+        Source { name: "<code>".to_string(), bytes: code.into_bytes(),
+                 from_file: false }
+    } else if let Some(name) = matches.free.first() {
+        if name == "-" {
+            let mut bytes = Vec::new();
+            if let Err(e) = io::stdin().lock().read_to_end(&mut bytes) {
+                fail("<stdin>", e.into());
+            }
+            return Source { name: "<stdin>".to_string(), bytes,
+                            from_file: false };
         }
+        match fs::read(name) {
+            Ok(bytes) => Source { name: name.clone(), bytes, from_file: true },
+            Err(e)    => fail(name, e.into()),
+        }
+    } else {
+        usage_error("no program given, expected FILE, -e CODE or -");
+    }
+}
+
+/// Returns the `ParseOptions` given by the options.
+fn parse_options(matches: &Matches) -> ParseOptions {
+    ParseOptions {
+        dump: matches.opt_present("dump"),
+        separator: matches.opt_present("separator"),
     }
+}
 
-    // opt-level
-    let opt_level = match matches.opt_str("O") {
-        Some(level) => match &level[..] {
-            "0" => OptLevel::No,
-            "1" => OptLevel::Less,
-            "3" => OptLevel::Aggressive,
-            _   => OptLevel::Default,
+/// Parses the program in `source`, returning its `Ast` optimized at
+/// `opt_level` for cells with the `overflow` policy, and the rest of the
+/// source if it stopped at a separator.
+fn parse(matches: &Matches, source: &Source, opt_level: OptLevel,
+         overflow: Overflow) -> (Ast, Option<Vec<u8>>) {
+    let options = parse_options(matches);
+    let mut byte_stream = ByteStream::new(&source.bytes[..]);
+    let ast = match parse_with(&mut byte_stream, &options) {
+        Ok(ast) => {
+            let options = OptimizeOptions { overflow, ..Default::default() };
+            optimize_with(opt_level, &ast, &options)
         },
+        Err(e)  => fail(&source.name, e),
+    };
+    let rest = if options.separator {
+        Some(byte_stream.collect())
+    } else {
+        None
+    };
+    (ast, rest)
+}
+
+/// Returns the `OptLevel` given by the options.
+fn opt_level(matches: &Matches) -> OptLevel {
+    match matches.opt_str("O") {
+        Some(level) => choice("optimization level", &level, &[
+            ("0", OptLevel::No),
+            ("1", OptLevel::Less),
+            ("2", OptLevel::Default),
+            ("3", OptLevel::Aggressive),
+        ]),
         None => OptLevel::Default,
+    }
+}
+
+/// Returns the `Config` of the tape given by the options.
+fn tape_config(matches: &Matches) -> Config {
+    let mut config: Config = Default::default();
+
+    // tape size, which must fit in memory, as the tape is allocated at once
+    let fits = |&size: &usize| {
+        size > 0 && Vec::<u64>::new().try_reserve_exact(size).is_ok()
     };
+    if let Some(size) = number(matches, "tape-size", "tape size", fits) {
+        config.tape_size = size;
+    }
 
-    // file name
-    let file_name = if !matches.free.is_empty() {
-        matches.free[0].clone()
+    // cell width
+    if let Some(bits) = matches.opt_str("cell-width") {
+        config.cell_width = choice("cell width", &bits, &[
+            ("8",  CellWidth::W8),
+            ("16", CellWidth::W16),
+            ("32", CellWidth::W32),
+            ("64", CellWidth::W64),
+        ]);
+    }
+
+    // overflow policy
+    if let Some(policy) = matches.opt_str("overflow") {
+        config.overflow = choice("overflow policy", &policy, &[
+            ("wrap",     Overflow::Wrap),
+            ("saturate", Overflow::Saturate),
+            ("error",    Overflow::Error),
+        ]);
+    }
+
+    // eof policy
+    if let Some(policy) = matches.opt_str("eof") {
+        config.eof = choice("eof policy", &policy, &[
+            ("zero",      Eof::Zero),
+            ("minus-one", Eof::MinusOne),
+            ("unchanged", Eof::Unchanged),
+        ]);
+    }
+
+    // the ends of the tape, for the commands which take them
+    if matches.opt_defined("out-of-bounds") {
+        bounds_config(matches, &mut config);
+    }
+    config
+}
+
+/// Sets the tape mode and out of bounds policy of `config` given by the
+/// options.
+fn bounds_config(matches: &Matches, config: &mut Config) {
+    // tape mode
+    if matches.opt_present("grow") || matches.opt_present("grow-left") {
+        let size = config.tape_size;
+        let max = number(matches, "max-tape-size", "max tape size",
+                         |&max| max >= size);
+        config.tape_mode = TapeMode::Growable {
+            left: matches.opt_present("grow-left"),
+            max: max.unwrap_or(cmp::max(DEFAULT_MAX_TAPE_SIZE, size)),
+        };
+    }
+
+    // out of bounds policy
+    if let Some(policy) = matches.opt_str("out-of-bounds") {
+        config.out_of_bounds = choice("out of bounds policy", &policy, &[
+            ("error", OutOfBounds::Error),
+            ("wrap",  OutOfBounds::Wrap),
+            ("clamp", OutOfBounds::Clamp),
+        ]);
+    }
+}
+
+/// Returns the `Encoding` given by the options.
+fn encoding(matches: &Matches) -> Encoding {
+    if matches.opt_present("utf8") {
+        Encoding::Utf8
     } else {
-        help(&program, opts);
+        Encoding::Bytes
+    }
+}
+
+/// Returns the `Limits` given by the options.
+fn limits(matches: &Matches) -> Limits {
+    // a timeout too long for a Duration is invalid rather than a panic
+    let timeout = number(matches, "timeout", "timeout", |&secs: &f64| {
+        secs > 0.0 && Duration::try_from_secs_f64(secs).is_ok()
+    });
+    Limits {
+        max_steps: number(matches, "max-steps", "max steps", |_| true),
+        timeout: timeout.map(Duration::from_secs_f64),
+        max_input: number(matches, "max-input", "max input", |_| true),
+        max_output: number(matches, "max-output", "max output", |_| true),
+    }
+}
+
+/// Returns the input of the program given after a separator in its source,
+/// in a file or as a string, which replaces stdin.
+fn input(matches: &Matches, rest: Option<Vec<u8>>) -> Option<Vec<u8>> {
+    let mut inputs: Vec<Vec<u8>> = rest.into_iter().collect();
+    if let Some(input_name) = matches.opt_str("input") {
+        match fs::read(&input_name) {
+            Ok(input) => inputs.push(input),
+            Err(e)    => fail(&input_name, e.into()),
+        }
+    }
+    if let Some(input) = matches.opt_str("input-string") {
+        inputs.push(input.into_bytes());
+    }
+    if inputs.len() > 1 {
+        usage_error("only one of --separator, --input and --input-string may \
+                     be given");
+    }
+    inputs.pop()
+}
+
+/// Reads, parses and optimizes the program, returning its source and an
+/// `Interpreter` of it, fed with any input given by the options.
+fn interpreter(matches: &Matches) -> (Source, Interpreter) {
+    let source = read_source(matches);
+    let config = tape_config(matches);
+    let (ast, rest) = parse(matches, &source, opt_level(matches),
+                            config.overflow);
+    let mut interpreter = Interpreter::new(&ast, &config, encoding(matches));
+    if let Some(input) = input(matches, rest) {
+        interpreter.feed_input(&input);
+        interpreter.close_input();
+    }
+    (source, interpreter)
+}
+
+/// Reports the error a run of `interpreter` failed with and exits. A run
+/// stopped by a limit also reports where the program was.
+fn run_error(source: &Source, interpreter: &Interpreter, e: RuntimeError)
+             -> ! {
+    if !e.error.is_limit() {
+        fail(&source.name, e.into());
+    }
+    eprintln!("error: {}:{}", source.name, e);
+    eprintln!("note: stopped after {} steps at instruction {}, with the \
+               pointer at cell {}", interpreter.steps(), interpreter.pc(),
+              interpreter.mem().position());
+    process::exit(EXIT_LIMIT);
+}
+
+/// Runs a program, or starts a REPL without one.
+fn run(matches: &Matches) {
+    if has_source(matches) {
+        let (source, mut interpreter) = interpreter(matches);
+        interpreter.set_limits(limits(matches));
+        let result = interpreter.run_with(&mut io::stdin().lock(),
+                                          &mut io::stdout().lock());
+        if let Err(e) = result {
+            run_error(&source, &interpreter, e);
+        }
         return;
-    };
+    }
 
-    // parse file and produce ast
-    let ast = match File::open(&Path::new(&file_name)) {
-        Ok(file) => {
-            let mut byte_stream = ByteStream::new(file);
-            optimize(opt_level, &parse(&mut byte_stream))
-        },
-        Err(e) => panic!("{}", e),
+    // without a program, run lines of one as they are entered
+    for name in ["separator", "input", "input-string"] {
+        if matches.opt_present(name) {
+            usage_error(&format!("--{} needs a program", name));
+        }
+    }
+    let mut repl = Repl::new(&tape_config(matches), encoding(matches),
+                             opt_level(matches), parse_options(matches));
+    let result = repl.run(&mut io::stdin().lock(), &mut io::stdout().lock());
+    if let Err(e) = result {
+        eprintln!("error: {}", e);
+        process::exit(EXIT_IO);
+    }
+}
+
+/// Emits a program as a file of each target.
+fn emit(matches: &Matches) {
+    let targets = match matches.opt_str("target") {
+        Some(targets) => targets,
+        None => usage_error("expected --target, one or more of c, ir and rust"),
     };
+    let targets: Vec<&str> = targets.split(',').map(|target| {
+        choice("emit target", target, &[
+            ("c",    "c"),
+            ("ir",   "ir"),
+            ("rust", "rust"),
+        ])
+    }).collect();
 
-    // evaluate or emit
-    if emit_targets.is_empty() {
-        eval(&ast);
-    } else {
-        for target in emit_targets.iter() {
-            match *target {
-                "c"    => emit_c(&file_name[..], &ast),
-                "ir"   => emit_ir(&file_name[..], &ast),
-                "rust" => emit_rust(&file_name[..], &ast),
-                _ => panic!("error: unknown emit type!"),
-            }
+    let source = read_source(matches);
+    if !source.from_file {
+        usage_error("emit needs a FILE to name the files it emits");
+    }
+    let config = tape_config(matches);
+    let (ast, _) = parse(matches, &source, opt_level(matches),
+                         config.overflow);
+    for target in targets {
+        let (result, extension) = match target {
+            "c"  => (emit_c(&source.name, &ast, &config), "c"),
+            "ir" => (emit_ir(&source.name, &ast), "ir"),
+            _    => (emit_rust(&source.name, &ast, &config), "rs"),
+        };
+        if let Err(e) = result {
+            fail(&format!("{}.{}", source.name, extension), e.into());
         }
     }
 }
+
+/// Prints a program formatted.
+fn fmt(matches: &Matches) {
+    let width = number(matches, "width", "width", |&w| w > 0).unwrap_or(80);
+    let source = read_source(matches);
+    let (ast, _) = parse(matches, &source, OptLevel::No, Overflow::Wrap);
+    let formatted = format(&ast, width)
+        .expect("an Ast which is not optimized can always be formatted");
+    let mut out = io::stdout().lock();
+    check_stdout(out.write_all(formatted.as_bytes())
+                    .and_then(|_| out.flush()));
+}
+
+/// Warns of likely mistakes in a program.
+fn lint(matches: &Matches) {
+    let source = read_source(matches);
+    let (ast, _) = parse(matches, &source, OptLevel::No, Overflow::Wrap);
+    let lints = brainfuck::lint(&ast);
+    for lint in lints.iter() {
+        eprintln!("warning: {}:{}", source.name, lint);
+    }
+    if !lints.is_empty() {
+        process::exit(EXIT_LINT);
+    }
+}
+
+/// Runs a program in the debugger.
+fn debug(matches: &Matches) {
+    if matches.free.first().is_some_and(|name| name == "-") {
+        usage_error("debug reads its commands from stdin, so can not read \
+                     the program from it");
+    }
+    let (source, interpreter) = interpreter(matches);
+    let mut debugger = Debugger::new(interpreter, &source.bytes);
+    let result = debugger.run(&mut io::stdin().lock(),
+                              &mut io::stdout().lock());
+    if let Err(e) = result {
+        eprintln!("error: {}", e);
+        process::exit(EXIT_IO);
+    }
+}
+
+/// Runs a program, then reports the instructions it executed most.
+fn profile(matches: &Matches) {
+    let top = number(matches, "top", "number of instructions", |_| true)
+        .unwrap_or(10);
+    let (source, mut interpreter) = interpreter(matches);
+    interpreter.set_limits(limits(matches));
+    interpreter.enable_profile();
+    let start = Instant::now();
+    let result = interpreter.run_with(&mut io::stdin().lock(),
+                                      &mut io::stdout().lock());
+    let elapsed = start.elapsed();
+
+    // the report follows the output of the program, even if it failed
+    let _ = io::stdout().flush();
+    let counts = interpreter.profile().unwrap_or(&[]);
+    let mut hottest: Vec<usize> = (0..counts.len()).collect();
+    hottest.sort_by_key(|&pc| cmp::Reverse(counts[pc]));
+    let steps = cmp::max(interpreter.steps(), 1) as f64;
+    eprintln!("{} steps in {:?}", interpreter.steps(), elapsed);
+    eprintln!("{:>12} {:>7}  instruction", "count", "%");
+    for &pc in hottest.iter().take(top) {
+        let instr = interpreter.code()[pc];
+        eprintln!("{:>12} {:>6.2}%  {}: {}:{}: {:?}", counts[pc],
+                  counts[pc] as f64 * 100.0 / steps, pc, source.name,
+                  instr.span, instr.op);
+    }
+
+    if let Err(e) = result {
+        run_error(&source, &interpreter, e);
+    }
+}
+
+/// Times a number of runs of a program.
+fn bench(matches: &Matches) {
+    let runs = number(matches, "runs", "number of runs", |&n| n > 0)
+        .unwrap_or(10);
+    let source = read_source(matches);
+    let config = tape_config(matches);
+    let (ast, rest) = parse(matches, &source, opt_level(matches),
+                            config.overflow);
+    let input = input(matches, rest).unwrap_or_default();
+    let limits = limits(matches);
+
+    let mut times = Vec::new();
+    let mut steps = 0;
+    for _ in 0..runs {
+        let mut interpreter = Interpreter::new(&ast, &config,
+                                               encoding(matches));
+        interpreter.set_limits(limits);
+        interpreter.feed_input(&input);
+        interpreter.close_input();
+
+        let start = Instant::now();
+        let result = interpreter.run_with(&mut io::empty(), &mut io::sink());
+        times.push(start.elapsed());
+        if let Err(e) = result {
+            run_error(&source, &interpreter, e);
+        }
+        steps = interpreter.steps();
+    }
+
+    let total: Duration = times.iter().sum();
+    let mut out = io::stdout().lock();
+    let result = writeln!(out, "{} runs of {} steps", runs, steps)
+        .and_then(|_| {
+            writeln!(out, "min {:?}, mean {:?}, max {:?}",
+                     times.iter().min().unwrap(), total / runs,
+                     times.iter().max().unwrap())
+        });
+    check_stdout(result);
+}
+
+/// Main function.
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    let program = &args[0].clone();
+
+    // without a command, start a REPL
+    let name = match args.get(1) {
+        Some(name) => &name[..],
+        None       => "run",
+    };
+    match name {
+        "-h" | "--help"    => check_stdout(help(program)),
+        "-v" | "--version" => check_stdout(version(program)),
+        "help" => match args.get(2) {
+            Some(name) => match COMMANDS.iter().find(|c| c.name == name) {
+                Some(command) => check_stdout(command_help(program, command)),
+                None          => unknown_command(name),
+            },
+            None => check_stdout(help(program)),
+        },
+        _ => match COMMANDS.iter().find(|c| c.name == name) {
+            Some(command) => {
+                let args = args.get(2..).unwrap_or_default();
+                let matches = parse_args(command, args);
+                if matches.opt_present("h") {
+                    check_stdout(command_help(program, command));
+                } else {
+                    (command.main)(&matches);
+                }
+            },
+            None => unknown_command(name),
+        },
+    }
+}
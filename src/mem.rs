@@ -1,56 +1,408 @@
-use std::num::SignedInt;
+use std::cmp;
+use std::fmt;
 
-use syntax::{Dir, Left, Right};
+use crate::syntax::{Dir, Left, Right};
 
-// size of allocated memory in bytes
-const MEM_SIZE: usize = 65_536; // 64kB!
+/// Default number of cells in the tape.
+pub const DEFAULT_TAPE_SIZE: usize = 65_536; // 64kB!
 
+/// Default maximum number of cells a growable tape may grow to.
+pub const DEFAULT_MAX_TAPE_SIZE: usize = 16_777_216; // 16MB!
+
+/// Number of cells either side of the pointer shown by a tape dump, by the
+/// interpreter, the debugger, the REPL and emitted programs alike.
+pub const DUMP_WINDOW: usize = 8;
+
+/// How the tape behaves when the pointer moves past either end of it.
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+pub enum TapeMode {
+    /// The tape has a fixed number of cells.
+    Fixed,
+    /// The tape grows on demand to the right, and to the left as well when
+    /// `left` is true, until it holds `max` cells.
+    Growable {
+        /// Whether the tape also grows to the left.
+        left: bool,
+        /// Most cells the tape may hold.
+        max: usize,
+    },
+}
+
+/// Number of bits in each cell of the tape.
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+pub enum CellWidth {
+    /// 8 bit cells.
+    W8,
+    /// 16 bit cells.
+    W16,
+    /// 32 bit cells.
+    W32,
+    /// 64 bit cells.
+    W64,
+}
+
+impl CellWidth {
+    /// Returns the number of bits in a cell.
+    pub fn bits(self) -> usize {
+        match self {
+            CellWidth::W8  => 8,
+            CellWidth::W16 => 16,
+            CellWidth::W32 => 32,
+            CellWidth::W64 => 64,
+        }
+    }
+
+    /// Returns the largest value a cell can hold, which is also the mask that
+    /// wraps a value to the width.
+    pub fn max(self) -> u64 {
+        match self {
+            CellWidth::W8  => 0xff,
+            CellWidth::W16 => 0xffff,
+            CellWidth::W32 => 0xffff_ffff,
+            CellWidth::W64 => 0xffff_ffff_ffff_ffff,
+        }
+    }
+
+    /// Returns the `CellWidth` with a number of `bits`, if there is one.
+    pub fn from_bits(bits: usize) -> Option<CellWidth> {
+        match bits {
+            8  => Some(CellWidth::W8),
+            16 => Some(CellWidth::W16),
+            32 => Some(CellWidth::W32),
+            64 => Some(CellWidth::W64),
+            _  => None,
+        }
+    }
+}
+
+/// What happens when a cell is incremented past its largest value or
+/// decremented past 0.
+///
+/// Only when cells wrap does the optimizer cancel out runs of `+` and `-` or
+/// treat `[+]` as clearing the cell, given the policy in `OptimizeOptions`.
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+pub enum Overflow {
+    /// The value wraps around, as with unsigned integer arithmetic.
+    Wrap,
+    /// The value stays at the largest value or at 0.
+    Saturate,
+    /// Evaluation stops with a `MemError`.
+    Error,
+}
+
+/// What happens when the pointer moves past either end of a tape that can not
+/// grow any further.
+///
+/// The optimizer replaces copy and multiplication loops with single
+/// instructions, so with `Clamp` an optimized program may touch different
+/// cells near the edges than the loops it replaced.
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+pub enum OutOfBounds {
+    /// Evaluation stops with a `MemError`.
+    Error,
+    /// The pointer wraps around to the other end of the tape.
+    Wrap,
+    /// The pointer stays at the end of the tape.
+    Clamp,
+}
+
+/// What the `,` instruction stores in the current cell once input has ended.
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+pub enum Eof {
+    /// The cell is set to 0.
+    Zero,
+    /// The cell is set to -1, which is the largest value of the cell width.
+    MinusOne,
+    /// The cell is left unchanged.
+    Unchanged,
+}
+
+/// Error caused by an operation on `Mem`.
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+pub enum MemError {
+    /// A cell was incremented past its largest value.
+    Overflow,
+    /// A cell was decremented past 0.
+    Underflow,
+    /// The pointer moved off the left or right end of the tape.
+    OutOfBounds(Dir),
+}
+
+impl fmt::Display for MemError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            MemError::Overflow  => write!(f, "cell overflow"),
+            MemError::Underflow => write!(f, "cell underflow"),
+            MemError::OutOfBounds(Left) => {
+                write!(f, "pointer moved off the left end of the tape")
+            },
+            MemError::OutOfBounds(Right) => {
+                write!(f, "pointer moved off the right end of the tape")
+            },
+        }
+    }
+}
+
+impl std::error::Error for MemError {}
+
+/// Configuration of the tape and its cells, shared by `Mem` and the emitters.
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+pub struct Config {
+    /// Number of cells the tape starts with, of which there is at least one.
+    pub tape_size: usize,
+    /// How the tape grows.
+    pub tape_mode: TapeMode,
+    /// Number of bits in each cell.
+    pub cell_width: CellWidth,
+    /// What happens when a cell overflows.
+    pub overflow: Overflow,
+    /// What happens when the pointer moves off the tape.
+    pub out_of_bounds: OutOfBounds,
+    /// What is stored when reading past the end of input.
+    pub eof: Eof,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            tape_size: DEFAULT_TAPE_SIZE,
+            tape_mode: TapeMode::Fixed,
+            cell_width: CellWidth::W8,
+            overflow: Overflow::Wrap,
+            out_of_bounds: OutOfBounds::Error,
+            eof: Eof::Unchanged,
+        }
+    }
+}
+
+/// Tape of cells and the pointer into it, which behaves according to the
+/// policies of the `Config` it was created from.
 pub struct Mem {
-    cells: Box<[u8]>,   // address space
-    ptr: usize          // pointer in address space
+    cells: Vec<u64>,    // address space
+    ptr: usize,         // pointer in address space
+    origin: usize,      // index of the cell the pointer started at
+    mode: TapeMode,     // how the address space grows
+    max: u64,           // largest value of a cell
+    overflow: Overflow, // what happens when a cell overflows
+    out_of_bounds: OutOfBounds, // what happens when the pointer leaves
+    eof: Eof,           // what is stored once input ends
 }
 
 impl Mem {
-    /// Create a new `Mem` stuct.
+    /// Create a new `Mem` stuct from `config`, with a single cell if its tape
+    /// size is 0.
     #[inline]
-    pub fn new() -> Mem {
+    pub fn new(config: &Config) -> Mem {
         Mem {
-            cells: box [0u8; MEM_SIZE],
-            ptr: 0
+            cells: vec![0u64; cmp::max(config.tape_size, 1)],
+            ptr: 0,
+            origin: 0,
+            mode: config.tape_mode,
+            max: config.cell_width.max(),
+            overflow: config.overflow,
+            out_of_bounds: config.out_of_bounds,
+            eof: config.eof,
+        }
+    }
+
+    /// Returns the position of the pointer relative to the cell it started at,
+    /// which is negative if the tape has grown to the left.
+    #[inline]
+    pub fn position(&self) -> isize {
+        self.ptr as isize - self.origin as isize
+    }
+
+    /// Returns the number of cells currently allocated.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.cells.len()
+    }
+
+    /// Returns true if no cells are allocated, which never happens as a tape
+    /// always holds at least one cell.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.cells.is_empty()
+    }
+
+    /// Grows the tape by at least `needed` cells to the left or right, doubling
+    /// its size where possible. Returns false if the tape can not grow that
+    /// much, or the cells can not be allocated, in which case it is left as it
+    /// is.
+    fn grow(&mut self, dir: Dir, needed: usize) -> bool {
+        let (left, max) = match self.mode {
+            TapeMode::Growable { left, max } => (left, max),
+            TapeMode::Fixed                  => return false,
+        };
+        if (dir == Left && !left) || self.cells.len() + needed > max {
+            return false;
+        }
+
+        let room = max - self.cells.len();
+        let extra = cmp::min(cmp::max(needed, self.cells.len()), room);
+        match dir {
+            Left => {
+                // prepend cells and re-base the pointer
+                let mut cells = Vec::new();
+                if cells.try_reserve_exact(self.cells.len() + extra).is_err() {
+                    return false;
+                }
+                cells.resize(extra, 0u64);
+                cells.extend_from_slice(&self.cells[..]);
+                self.cells = cells;
+                self.ptr += extra;
+                self.origin += extra;
+            },
+            Right => {
+                if self.cells.try_reserve_exact(extra).is_err() {
+                    return false;
+                }
+                self.cells.resize(self.cells.len() + extra, 0u64);
+            },
+        }
+        true
+    }
+
+    /// Returns the index of the cell left or right a number of steps from the
+    /// current pointer, growing the tape to hold it if possible and otherwise
+    /// applying the out of bounds policy.
+    fn index(&mut self, dir: Dir, steps: usize) -> Result<usize, MemError> {
+        let in_bounds = match dir {
+            Left => {
+                steps <= self.ptr || {
+                    let needed = steps - self.ptr;
+                    self.grow(Left, needed)
+                }
+            },
+            Right => {
+                self.ptr + steps < self.cells.len() || {
+                    let needed = self.ptr + steps + 1 - self.cells.len();
+                    self.grow(Right, needed)
+                }
+            },
+        };
+
+        let len = self.cells.len();
+        if in_bounds {
+            return match dir {
+                Left  => Ok(self.ptr - steps),
+                Right => Ok(self.ptr + steps),
+            };
+        }
+
+        match (self.out_of_bounds, dir) {
+            (OutOfBounds::Error, _)     => Err(MemError::OutOfBounds(dir)),
+            (OutOfBounds::Wrap, Left)   => Ok((self.ptr + len - steps % len) % len),
+            (OutOfBounds::Wrap, Right)  => Ok((self.ptr + steps) % len),
+            (OutOfBounds::Clamp, Left)  => Ok(0),
+            (OutOfBounds::Clamp, Right) => Ok(len - 1),
         }
     }
 
     /// Return the value of cell at the current pointer.
     #[inline]
-    pub fn get(&self) -> u8 {
+    pub fn get(&self) -> u64 {
         self.cells[self.ptr]
     }
 
-    /// Set the value at the current pointer.
+    /// Returns the value of the cell at `position`, relative to the cell the
+    /// pointer started at, or `None` if the tape does not hold it.
+    #[inline]
+    pub fn cell(&self, position: isize) -> Option<u64> {
+        let index = self.origin as isize + position;
+        if index < 0 {
+            return None;
+        }
+        self.cells.get(index as usize).cloned()
+    }
+
+    /// Returns the values of the cells up to `window` either side of the
+    /// pointer, separated by spaces, with the current cell in brackets.
+    pub fn window(&self, window: usize) -> String {
+        let pointer = self.position();
+        let window = window as isize;
+        let mut cells = Vec::new();
+        for position in pointer - window..=pointer + window {
+            match self.cell(position) {
+                Some(value) if position == pointer => {
+                    cells.push(format!("[{}]", value));
+                },
+                Some(value) => cells.push(value.to_string()),
+                None        => {},
+            }
+        }
+        cells.join(" ")
+    }
+
+    /// Set the value at the current pointer, wrapped to the cell width.
+    #[inline]
+    pub fn set(&mut self, value: u64) {
+        self.cells[self.ptr] = value & self.max;
+    }
+
+    /// Stores a `value` read from input in the current cell, or applies the EOF
+    /// policy if `value` is `None` because input has ended.
+    #[inline]
+    pub fn input(&mut self, value: Option<u64>) {
+        match (value, self.eof) {
+            (Some(value), _)      => self.set(value),
+            (None, Eof::Zero)     => self.set(0),
+            (None, Eof::MinusOne) => self.set(self.max),
+            (None, Eof::Unchanged) => {},
+        }
+    }
+
+    /// Returns the sum of `cell` and `value` according to the overflow policy.
     #[inline]
-    pub fn set(&mut self, value: u8) {
-        self.cells[self.ptr] = value;
+    fn sum(&self, cell: u64, value: u64) -> Result<u64, MemError> {
+        match self.overflow {
+            Overflow::Wrap => Ok(cell.wrapping_add(value) & self.max),
+            Overflow::Saturate => match cell.checked_add(value) {
+                Some(sum) if sum <= self.max => Ok(sum),
+                _                            => Ok(self.max),
+            },
+            Overflow::Error => match cell.checked_add(value) {
+                Some(sum) if sum <= self.max => Ok(sum),
+                _                            => Err(MemError::Overflow),
+            },
+        }
+    }
+
+    /// Returns the difference of `cell` and `value` according to the overflow
+    /// policy.
+    #[inline]
+    fn difference(&self, cell: u64, value: u64) -> Result<u64, MemError> {
+        match self.overflow {
+            Overflow::Wrap => Ok(cell.wrapping_sub(value) & self.max),
+            Overflow::Saturate => Ok(cell.saturating_sub(value)),
+            Overflow::Error => match cell.checked_sub(value) {
+                Some(difference) => Ok(difference),
+                None             => Err(MemError::Underflow),
+            },
+        }
     }
 
     /// Adds `value` to the current cell.
     #[inline]
-    pub fn add(&mut self, value: u8) {
-        self.cells[self.ptr] += value;
+    pub fn add(&mut self, value: u64) -> Result<(), MemError> {
+        let cell = self.cells[self.ptr];
+        self.cells[self.ptr] = self.sum(cell, value)?;
+        Ok(())
     }
 
     /// Subtracts `value` from the current cell.
     #[inline]
-    pub fn subtract(&mut self, value: u8) {
-        self.cells[self.ptr] -= value;
+    pub fn subtract(&mut self, value: u64) -> Result<(), MemError> {
+        let cell = self.cells[self.ptr];
+        self.cells[self.ptr] = self.difference(cell, value)?;
+        Ok(())
     }
 
     /// Shifts the current pointer to the left or right by a number of steps.
     #[inline]
-    pub fn shift(&mut self, dir: Dir, steps: usize) {
-        match dir {
-            Left  => self.ptr -= steps,
-            Right => self.ptr += steps,
-        }
+    pub fn shift(&mut self, dir: Dir, steps: usize) -> Result<(), MemError> {
+        self.ptr = self.index(dir, steps)?;
+        Ok(())
     }
 
     // optimizations
@@ -61,60 +413,67 @@ impl Mem {
         self.cells[self.ptr] = 0;
     }
 
-    /// Scans left or right for a zero cell. This fuction will panic! if there
-    /// is no zero cell before it scans past the beginning of the address space.
+    /// Scans left or right for a zero cell, applying the out of bounds policy
+    /// if it scans past the end of a tape that can not grow. Returns false if
+    /// no zero cell will ever be found, as the pointer is clamped at the end of
+    /// the tape or has wrapped around to where it started.
     #[inline]
-    pub fn scan(&mut self, dir: Dir) {
+    pub fn scan(&mut self, dir: Dir) -> Result<bool, MemError> {
+        let start = self.position();
         while self.cells[self.ptr] != 0 {
-            self.shift(dir, 1);
+            let position = self.position();
+            self.shift(dir, 1)?;
+            if self.position() == position || self.position() == start {
+                return Ok(false);
+            }
         }
+        Ok(true)
     }
 
     /// Copys the value of the current cell into the cell left or right a
     /// number of steps.
     #[inline]
-    pub fn copy(&mut self, dir: Dir, steps: usize) {
-        let index = match dir {
-            Left  => self.ptr - steps,
-            Right => self.ptr + steps,
-        };
-        self.cells[index] += self.cells[self.ptr];
+    pub fn copy(&mut self, dir: Dir, steps: usize) -> Result<(), MemError> {
+        self.multiply(dir, steps, 1)
     }
 
     /// Multiplys the value of the current cell by a factor and inserts the
-    /// product into the cell left or right a number of steps.
-    pub fn multiply(&mut self, dir: Dir, steps: usize, factor: i8) {
-        let index = match dir {
-            Left  => self.ptr - steps,
-            Right => self.ptr + steps,
-        };
-
-        // safely cast factor to u8
-        let u8_factor = SignedInt::abs(factor) as u8;
-
-        // when factor is 1 it acts like a copy
-        if factor == 1 {
-            self.cells[index] += self.cells[self.ptr];
+    /// product into the cell left or right a number of steps. A negative
+    /// factor subtracts the product instead.
+    pub fn multiply(&mut self, dir: Dir, steps: usize, factor: i64)
+                    -> Result<(), MemError> {
+        // like the loop it replaces, nothing happens when the cell is 0
+        let cell = self.cells[self.ptr];
+        if cell == 0 {
+            return Ok(());
         }
 
-        // when factor is -1 it acts like the inverse of copy
-        else if factor == -1 {
-            self.cells[index] -= self.cells[self.ptr];
-        }
+        let index = self.index(dir, steps)?;
+        let target = self.cells[index];
 
-        // when factor is >= 2 it adds the product of the current cell and the
-        // absolute value of factor to the cell at index
-        else if factor >= 2 {
-            self.cells[index] += self.cells[self.ptr] * u8_factor;
-        }
+        // the absolute value of factor, computed so it can not overflow
+        let abs_factor = if factor < 0 {
+            0u64.wrapping_sub(factor as u64)
+        } else {
+            factor as u64
+        };
 
-        // when factor is <= -2 it subtracts the product of the current cell and the
-        // absolute value of factor to the cell at index
-        else if factor <= -2 {
-            self.cells[index] -= self.cells[self.ptr] * u8_factor;
-        }
+        // a product too large for any cell overflows, unless it wraps
+        let product = match self.overflow {
+            Overflow::Wrap => cell.wrapping_mul(abs_factor),
+            _ => match cell.checked_mul(abs_factor) {
+                Some(product) => product,
+                None if self.overflow == Overflow::Saturate => u64::MAX,
+                None if factor < 0 => return Err(MemError::Underflow),
+                None => return Err(MemError::Overflow),
+            },
+        };
 
-        // when factor is 0 it is ignored, as it would do nothing
-        else {}
+        self.cells[index] = if factor < 0 {
+            self.difference(target, product)?
+        } else {
+            self.sum(target, product)?
+        };
+        Ok(())
     }
 }
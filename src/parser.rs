@@ -1,41 +1,147 @@
-use byte_stream::ByteStream;
-use syntax::{Ast, Ir, Left, Right};
+use std::fmt;
+use std::io::Read;
+use std::mem;
 
-/// Parses a `TokenStream` and returns a vaid `Ast`, panics if there is a
-/// syntax error.
-pub fn parse<R: Reader>(byte_stream: &mut ByteStream<R>) -> Ast {
+use crate::byte_stream::ByteStream;
+use crate::error::Error;
+use crate::syntax::{Ast, Ir, Left, Node, Position, Right, Span};
+
+/// Syntax error found while parsing.
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+pub enum ParseError {
+    /// A `[` that is never closed, along with the position of the last `]` in
+    /// the source, which is the most likely candidate for its partner.
+    UnmatchedOpen(Position, Option<Position>),
+    /// A `]` that closes nothing, along with the position of the `[` of the
+    /// most recently closed loop, which is the most likely candidate for its
+    /// partner.
+    UnmatchedClose(Position, Option<Position>),
+}
+
+impl ParseError {
+    /// Returns the position of the offending bracket.
+    pub fn position(&self) -> Position {
+        match *self {
+            ParseError::UnmatchedOpen(pos, _)  => pos,
+            ParseError::UnmatchedClose(pos, _) => pos,
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ParseError::UnmatchedOpen(_, candidate) => {
+                write!(f, "unmatched '['")?;
+                match candidate {
+                    Some(c) => write!(f, " (nearest ']' is at {})", c),
+                    None    => Ok(()),
+                }
+            },
+            ParseError::UnmatchedClose(_, candidate) => {
+                write!(f, "unmatched ']'")?;
+                match candidate {
+                    Some(c) => write!(f, " (nearest '[' is at {})", c),
+                    None    => Ok(()),
+                }
+            },
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Options of the parser, which enable extensions to the language.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct ParseOptions {
+    /// Parse `#` as `Ir::Dump` instead of a comment.
+    pub dump: bool,
+    /// Stop at the first `!`, which separates the program from its input,
+    /// leaving the rest of the `ByteStream` unread.
+    pub separator: bool,
+}
+
+/// Parses a `ByteStream` and returns a valid `Ast`, or an `Error` holding
+/// either a `ParseError` with the position of the first unmatched bracket, or
+/// the `io::Error` which stopped reading the stream.
+pub fn parse<R: Read>(byte_stream: &mut ByteStream<R>) -> Result<Ast, Error> {
+    parse_with(byte_stream, &Default::default())
+}
+
+/// Parses a `ByteStream` like `parse`, with the extensions enabled by
+/// `options`.
+pub fn parse_with<R: Read>(byte_stream: &mut ByteStream<R>,
+                           options: &ParseOptions) -> Result<Ast, Error> {
     let mut ast = Vec::new();
-    let mut open_count = 0u32;
-    let mut close_count = 0u32;
-    loop {
-        match byte_stream.next() {
-            Some(byte) => match byte as char {
-                '+' => ast.push(Ir::Add(1u8)),
-                '-' => ast.push(Ir::Sub(1u8)),
-                '<' => ast.push(Ir::Shift(Left, 1us)),
-                '>' => ast.push(Ir::Shift(Right, 1us)),
-                ',' => ast.push(Ir::Read),
-                '.' => ast.push(Ir::Write),
-                '[' => {
-                    open_count += 1;
-                    ast.push(Ir::Open);
-                },
-                ']' => {
-                    close_count += 1;
-                    ast.push(Ir::Close)
-                },
-                _  => {} // ignore all other characters
+
+    // positions of the currently open '[' along with the enclosing ast of
+    // each, the '[' of the most recently closed loop and the last ']'
+    let mut open_stack = Vec::new();
+    let mut last_open = None;
+    let mut last_close = None;
+
+    let mut pos = Position { offset: 0, line: 1, col: 1 };
+    for byte in byte_stream.by_ref() {
+        let span = Span::at(pos);
+        match byte as char {
+            '+' => ast.push(Node::new(Ir::Add(1u64), span)),
+            '-' => ast.push(Node::new(Ir::Sub(1u64), span)),
+            '<' => ast.push(Node::new(Ir::Shift(Left, 1), span)),
+            '>' => ast.push(Node::new(Ir::Shift(Right, 1), span)),
+            ',' => ast.push(Node::new(Ir::Read, span)),
+            '.' => ast.push(Node::new(Ir::Write, span)),
+            '#' if options.dump => ast.push(Node::new(Ir::Dump, span)),
+            '!' if options.separator => break, // the rest is input
+            '[' => {
+                let outer = mem::take(&mut ast);
+                open_stack.push((pos, outer));
+            },
+            ']' => {
+                let (open, outer) = match open_stack.pop() {
+                    Some(frame) => frame,
+                    None => {
+                        let error = ParseError::UnmatchedClose(pos, last_open);
+                        return Err(error.into());
+                    },
+                };
+                let body = mem::replace(&mut ast, outer);
+                let span = Span { start: open, end: pos };
+                ast.push(Node::new(Ir::Loop(body), span));
+                last_open = Some(open);
+                last_close = Some(pos);
             },
-            None => break, // eof
+            _  => {} // ignore all other characters
         }
+
+        // advance the position
+        pos.offset += 1;
+        if byte == b'\n' {
+            pos.line += 1;
+            pos.col = 1;
+        } else {
+            pos.col += 1;
+        }
+    }
+
+    // a stream which stopped early is missing the rest of the source, so it
+    // can not be told whether its brackets match
+    if let Some(e) = byte_stream.take_error() {
+        return Err(e.into());
     }
 
-    // assert that there is a matching number of '[' and ']'
-    if open_count > close_count {
-        panic!("syntax error: unmatched '['");
-    } else if open_count < close_count {
-        panic!("syntax error: unmatched ']'");
+    // the outermost '[' left open is the one that is definitely unmatched
+    if !open_stack.is_empty() {
+        let open = open_stack[0].0;
+
+        // only a ']' after the '[' could have been meant to close it
+        let candidate = match last_close {
+            Some(close) if close.offset > open.offset => {
+                Some(close)
+            },
+            _ => None,
+        };
+        Err(ParseError::UnmatchedOpen(open, candidate).into())
     } else {
-        ast
+        Ok(ast)
     }
 }
@@ -0,0 +1,114 @@
+use std::io::{self, BufRead, Write};
+
+use crate::byte_stream::ByteStream;
+use crate::error::Error;
+use crate::eval::{Encoding, Interpreter, Status};
+use crate::mem::{Config, DUMP_WINDOW};
+use crate::optimizer::{optimize_with, OptLevel, OptimizeOptions};
+use crate::parser::{parse_with, ParseError, ParseOptions};
+
+/// Interactive read-eval-print loop, which parses, optimizes and runs each
+/// line of a program as it is entered, on a tape kept from one line to the
+/// next. A line which leaves a loop open is run once later lines close it.
+pub struct Repl {
+    interpreter: Interpreter,
+    opt_level: OptLevel,
+    options: ParseOptions,
+    optimize_options: OptimizeOptions,
+}
+
+impl Repl {
+    /// Create a new `Repl`, which runs on a tape created from `config`, reads
+    /// and writes cells with `encoding`, and parses each line with `options`
+    /// before optimizing it at `opt_level`.
+    pub fn new(config: &Config, encoding: Encoding, opt_level: OptLevel,
+               options: ParseOptions) -> Repl {
+        Repl {
+            interpreter: Interpreter::new(&Vec::new(), config, encoding),
+            opt_level,
+            options,
+
+            // earlier lines may have left the current cell non-zero
+            optimize_options: OptimizeOptions {
+                resumed: true,
+                overflow: config.overflow,
+            },
+        }
+    }
+
+    /// Returns the `Interpreter` running the lines.
+    pub fn interpreter(&self) -> &Interpreter {
+        &self.interpreter
+    }
+
+    /// Reads lines of a program from `lines` until it ends, running them once
+    /// their brackets balance, and writing the output of the program and the
+    /// tape around the pointer after each to `output`. When the program reads
+    /// and no input is left, the next line is fed to it as input.
+    pub fn run<R, W>(&mut self, lines: &mut R, output: &mut W)
+                     -> io::Result<()>
+        where R: BufRead, W: Write {
+        let mut source = String::new();
+        loop {
+            let prompt = if source.is_empty() { "bf> " } else { "... " };
+            write!(output, "{}", prompt)?;
+            output.flush()?;
+            if lines.read_line(&mut source)? == 0 {
+                return writeln!(output);
+            }
+
+            let mut byte_stream = ByteStream::new(source.as_bytes());
+            let ast = match parse_with(&mut byte_stream, &self.options) {
+                Ok(ast) => ast,
+
+                // wait for the lines which close the open loops
+                Err(Error::Parse(ParseError::UnmatchedOpen(..))) => continue,
+                Err(e) => {
+                    writeln!(output, "error: {}", e)?;
+                    source.clear();
+                    continue;
+                },
+            };
+            source.clear();
+
+            let ast = optimize_with(self.opt_level, &ast,
+                                    &self.optimize_options);
+            self.interpreter.load(&ast);
+            self.execute(lines, output)?;
+        }
+    }
+
+    /// Runs the loaded lines until they halt or fail, reading input from
+    /// `lines` as the program needs it, then shows the tape.
+    fn execute<R, W>(&mut self, lines: &mut R, output: &mut W)
+                     -> io::Result<()>
+        where R: BufRead, W: Write {
+        loop {
+            let status = self.interpreter.run();
+            self.interpreter.write_pending_lines(output)?;
+
+            match status {
+                Status::Halted | Status::OutOfFuel => break,
+                Status::NeedsInput => {
+                    write!(output, "input> ")?;
+                    output.flush()?;
+                    let mut input = String::new();
+                    if lines.read_line(&mut input)? == 0 {
+                        writeln!(output)?;
+                        self.interpreter.close_input();
+                    } else {
+                        self.interpreter.feed_input(input.as_bytes());
+                    }
+                },
+                Status::Error(e) => {
+                    writeln!(output, "error: {}", e)?;
+                    break;
+                },
+            }
+        }
+
+        let mem = self.interpreter.mem();
+        writeln!(output, "pointer at cell {}: {}", mem.position(),
+                 mem.window(DUMP_WINDOW))
+    }
+}
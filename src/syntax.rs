@@ -1,30 +1,95 @@
+use std::fmt;
+
 // re-export Left and Right
 pub use self::Dir::{Left, Right};
 
+/// Location of a byte in the source. The byte `offset` starts at 0, while the
+/// `line` and `col` start at 1.
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+pub struct Position {
+    /// Offset of the byte from the start of the source.
+    pub offset: usize,
+    /// Line the byte is on.
+    pub line: usize,
+    /// Column of the byte in its line.
+    pub col: usize,
+}
+
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}", self.line, self.col)
+    }
+}
+
 /// Directions Left or Right.
 #[derive(Clone, Copy, Eq, PartialEq, Debug)]
 pub enum Dir {
+    /// Towards the start of the tape.
     Left,
+    /// Towards the end of the tape.
     Right,
 }
 
-/// Intermediate Representation of Brainfuck operations.
+/// Range of source from `start` to `end` inclusive, which an instruction was
+/// created from.
 #[derive(Clone, Copy, Eq, PartialEq, Debug)]
+pub struct Span {
+    /// Position of the first byte.
+    pub start: Position,
+    /// Position of the last byte.
+    pub end: Position,
+}
+
+impl Span {
+    /// Creates a `Span` covering the single byte at `pos`.
+    #[inline]
+    pub fn at(pos: Position) -> Span {
+        Span { start: pos, end: pos }
+    }
+
+    /// Returns a `Span` from the start of `self` to the end of `other`.
+    #[inline]
+    pub fn to(self, other: Span) -> Span {
+        Span { start: self.start, end: other.end }
+    }
+
+    /// Returns true if the span covers the source at `line` and `col`.
+    #[inline]
+    pub fn contains(self, line: usize, col: usize) -> bool {
+        (self.start.line, self.start.col) <= (line, col) &&
+            (line, col) <= (self.end.line, self.end.col)
+    }
+}
+
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.start == self.end {
+            write!(f, "{}", self.start)
+        } else {
+            write!(f, "{}-{}", self.start, self.end)
+        }
+    }
+}
+
+/// Intermediate Representation of Brainfuck operations.
+#[derive(Clone, Eq, PartialEq, Debug)]
 pub enum Ir {
-    /// Adds the value to the current cell.
-    Add(u8),
-    /// Subtracts the value from the current cell.
-    Sub(u8),
+    /// Adds the value to the current cell, wrapping at the cell width.
+    Add(u64),
+    /// Subtracts the value from the current cell, wrapping at the cell width.
+    Sub(u64),
     /// Shifts the pointer in memory left or right by a number of steps.
     Shift(Dir, usize),
     /// Reads and stores a single byte into the current cell.
     Read,
     /// Writes the byte at the current cell to `stdout` as a `char`.
     Write,
-    /// Opens a loop.
-    Open,
-    /// Closes a loop.
-    Close,
+    /// Repeats the body of the loop while the current cell is not 0.
+    Loop(Ast),
+    /// Dumps the pointer and the cells around it to `stderr`. It is only
+    /// parsed from `#` when asked for, and the optimizer never merges or
+    /// replaces instructions across it.
+    Dump,
 
     // optimizations
 
@@ -35,10 +100,29 @@ pub enum Ir {
     Copy(Dir, usize),
     /// Multiplies the value at the current cell by a specified factor and
     /// then stores the product in the cell left or right by a number of steps.
-    Mul(Dir, usize, i8),
+    Mul(Dir, usize, i64),
     /// Scans left or right in memory until the value in the current cell is 0.
     Scan(Dir),
 }
 
-/// Abstract Syntax Tree or `Ast`.
-pub type Ast = Vec<Ir>;
+/// An `Ir` instruction and the `Span` of source it was created from. The span
+/// of a loop covers everything from its `[` to its `]`.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct Node {
+    /// The instruction.
+    pub ir: Ir,
+    /// Source the instruction was created from.
+    pub span: Span,
+}
+
+impl Node {
+    /// Create a new `Node`.
+    #[inline]
+    pub fn new(ir: Ir, span: Span) -> Node {
+        Node { ir, span }
+    }
+}
+
+/// Abstract Syntax Tree or `Ast`, where each loop owns its body, so brackets
+/// are always matched.
+pub type Ast = Vec<Node>;
@@ -0,0 +1,168 @@
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+
+/// Runs `bfi` with `args`, writing `stdin` to it, and returns its output.
+fn bfi(args: &[&str], stdin: &[u8]) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_bfi"))
+        .args(args)
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    // bfi may exit without reading stdin, which breaks the pipe
+    let _ = child.stdin.take().unwrap().write_all(stdin);
+    child.wait_with_output().unwrap()
+}
+
+#[test]
+fn runs_a_file() {
+    let output = bfi(&["run", "examples/hello.b"], b"");
+    assert!(output.status.success());
+    assert_eq!(output.stdout, b"Hello World!\n".to_vec());
+}
+
+#[test]
+fn runs_inline_code() {
+    let output = bfi(&["run", "-e", "++++++++[>++++++++<-]>+."], b"");
+    assert!(output.status.success());
+    assert_eq!(output.stdout, b"A".to_vec());
+}
+
+#[test]
+fn runs_a_program_from_stdin() {
+    let output = bfi(&["run", "-"], b"++++++++[>++++++++<-]>++.");
+    assert!(output.status.success());
+    assert_eq!(output.stdout, b"B".to_vec());
+}
+
+#[test]
+fn reports_parse_errors_with_the_source() {
+    let output = bfi(&["run", "-e", "+["], b"");
+    assert_eq!(output.status.code(), Some(3));
+    assert_eq!(String::from_utf8_lossy(&output.stderr),
+               "error: <code>:1:2: unmatched '['\n");
+
+    let output = bfi(&["run", "-"], b"\n]");
+    assert_eq!(String::from_utf8_lossy(&output.stderr),
+               "error: <stdin>:2:1: unmatched ']'\n");
+}
+
+#[test]
+fn stops_at_limits() {
+    let output = bfi(&["run", "--max-steps", "50", "-e", "+[]"], b"");
+    assert_eq!(output.status.code(), Some(6));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.starts_with("error: <code>:1:3: step limit of 50 exceeded\n\
+                                 note: stopped after 50 steps"));
+}
+
+#[test]
+fn reads_input_from_a_string_or_file() {
+    let output = bfi(&["run", "--eof", "zero", "--input-string", "abc", "-"],
+                     b",[.,]");
+    assert!(output.status.success());
+    assert_eq!(output.stdout, b"abc".to_vec());
+
+    let output = bfi(&["run", "--eof", "zero", "--input", "examples/hello.b",
+                       "-e", ",[.,]"], b"ignored");
+    let hello = std::fs::read(concat!(env!("CARGO_MANIFEST_DIR"),
+                                      "/examples/hello.b")).unwrap();
+    assert_eq!(output.stdout, hello);
+}
+
+#[test]
+fn rejects_more_than_one_input() {
+    let output = bfi(&["run", "--input-string", "a", "--separator", "-e",
+                       ",."], b"");
+    assert_eq!(output.status.code(), Some(2));
+    assert!(output.stdout.is_empty());
+}
+
+#[test]
+fn starts_a_repl_without_a_program() {
+    let output = bfi(&[], b"++[\n>+++<-]>.\n");
+    assert!(output.status.success());
+    assert_eq!(output.stdout, b"bf> ... \x06\n\
+                                pointer at cell 1: 0 [6] 0 0 0 0 0 0 0 0\n\
+                                bf> \n".to_vec());
+}
+
+#[test]
+fn rejects_unknown_values_with_suggestions() {
+    let output = bfi(&["rnu", "examples/hello.b"], b"");
+    assert_eq!(output.status.code(), Some(2));
+    assert_eq!(String::from_utf8_lossy(&output.stderr),
+               "error: unknown command 'rnu', did you mean 'run'?\n");
+
+    let output = bfi(&["run", "--overflow", "wrapp", "-e", "+"], b"");
+    assert_eq!(String::from_utf8_lossy(&output.stderr),
+               "error: invalid overflow policy 'wrapp', did you mean 'wrap'?\n");
+
+    let output = bfi(&["run", "-O", "4", "-e", "+"], b"");
+    assert_eq!(String::from_utf8_lossy(&output.stderr),
+               "error: invalid optimization level '4', expected one of 0, 1, \
+                2, 3\n");
+
+    let output = bfi(&["emit", "--targte", "c", "examples/hello.b"], b"");
+    assert_eq!(String::from_utf8_lossy(&output.stderr),
+               "error: unknown option 'targte' for 'emit', did you mean \
+                '--target'?\n");
+}
+
+#[test]
+fn rejects_options_a_command_does_not_honour() {
+    let output = bfi(&["emit", "--out-of-bounds", "clamp", "-t", "c",
+                       "examples/hello.b"], b"");
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr)
+                .starts_with("error: unknown option 'out-of-bounds' for \
+                              'emit'"));
+
+    let output = bfi(&["debug", "-"], b"+.");
+    assert_eq!(output.status.code(), Some(2));
+    assert_eq!(String::from_utf8_lossy(&output.stderr),
+               "error: debug reads its commands from stdin, so can not read \
+                the program from it\n");
+}
+
+#[test]
+fn formats_and_lints_programs() {
+    let output = bfi(&["fmt", "-w", "6", "-e", "+++ add [->+<] >."], b"");
+    assert!(output.status.success());
+    assert_eq!(output.stdout, b"+++\n[\n  ->+<\n]\n>.\n".to_vec());
+
+    let output = bfi(&["lint", "-e", "+-[]"], b"");
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(String::from_utf8_lossy(&output.stderr),
+               "warning: <code>:1:1-1:2: instructions cancel each other out\n\
+                warning: <code>:1:3-1:4: empty loop never ends\n");
+}
+
+#[test]
+fn exits_with_a_status_for_each_kind_of_error() {
+    let output = bfi(&["run", "examples/missing.b"], b"");
+    assert_eq!(output.status.code(), Some(5));
+    assert!(String::from_utf8_lossy(&output.stderr)
+                .starts_with("error: examples/missing.b: "));
+
+    let output = bfi(&["run", "-e", "<"], b"");
+    assert_eq!(output.status.code(), Some(4));
+    assert_eq!(String::from_utf8_lossy(&output.stderr),
+               "error: <code>:1:1: pointer moved off the left end of the \
+                tape\n");
+
+    let output = bfi(&["run", "--tape-size", "0", "-e", "+"], b"");
+    assert_eq!(output.status.code(), Some(2));
+
+    let output = bfi(&["run", "--tape-size", "100000000000000000", "-e", "+"],
+                     b"");
+    assert_eq!(output.status.code(), Some(2));
+
+    let output = bfi(&["run", "--timeout", "1e30", "-e", "+"], b"");
+    assert_eq!(output.status.code(), Some(2));
+    assert_eq!(String::from_utf8_lossy(&output.stderr),
+               "error: invalid timeout '1e30'\n");
+}
@@ -0,0 +1,74 @@
+use brainfuck::{optimize, parse, Breakpoint, ByteStream, Debugger, Encoding};
+use brainfuck::{Interpreter, OptLevel};
+
+/// Debugs `source` optimized at `opt_level`, running `commands`, and returns
+/// the debugger and everything it wrote.
+fn debug(source: &str, opt_level: OptLevel, commands: &str)
+         -> (Debugger, String) {
+    let ast = parse(&mut ByteStream::new(source.as_bytes())).unwrap();
+    let ast = optimize(opt_level, &ast);
+    let interpreter = Interpreter::new(&ast, &Default::default(),
+                                       Encoding::Bytes);
+    let mut debugger = Debugger::new(interpreter, source.as_bytes());
+    let mut output = Vec::new();
+    debugger.run(&mut commands.as_bytes(), &mut output).unwrap();
+    (debugger, String::from_utf8(output).unwrap())
+}
+
+#[test]
+fn steps_and_shows_the_position() {
+    let (debugger, output) = debug("+\n+>+", OptLevel::No, "step 2\n");
+    assert_eq!(debugger.interpreter().pc(), 2);
+    assert!(output.contains("2:2: instruction 2: Shift(Right, 1)\n\
+                             \x20   +>+\n\
+                             \x20    ^\n"));
+}
+
+#[test]
+fn an_empty_line_repeats_the_last_command() {
+    let (debugger, _) = debug("++++", OptLevel::No, "s\n\n\n");
+    assert_eq!(debugger.interpreter().mem().get(), 3);
+}
+
+#[test]
+fn continues_to_a_source_breakpoint() {
+    let (debugger, output) = debug("+++[>+<-]>.", OptLevel::No,
+                                   "break 1:10\ncontinue\n");
+    assert_eq!(debugger.breakpoints(), &[Breakpoint::Source(1, 10)]);
+    assert_eq!(debugger.interpreter().pc(), 9);
+    assert!(output.contains("stopped at breakpoint 0"));
+}
+
+#[test]
+fn continues_to_an_instruction_breakpoint_in_optimized_code() {
+    let (debugger, output) = debug("+++[>++<-]>.", OptLevel::Aggressive,
+                                   "b 2\nc\np\nc\n");
+    assert!(debugger.interpreter().is_halted());
+    assert!(output.contains("stopped at breakpoint 0\n\
+                             1:4-1:10: instruction 2: Clear"));
+    assert!(output.contains("pointer at cell 0, which holds 3"));
+    assert!(output.contains("\u{6}\nhalted after 5 steps"));
+}
+
+#[test]
+fn prints_and_sets_cells() {
+    let (debugger, output) = debug(">+", OptLevel::No, "s 2\nset 300\ntape 1\n");
+    assert_eq!(debugger.interpreter().mem().get(), 300 % 256);
+    assert!(output.contains("       0: 0\n>      1: 44\n       2: 0\n"));
+}
+
+#[test]
+fn waits_for_input() {
+    let (_, output) = debug(",.,.", OptLevel::No,
+                            "c\ninput a\nc\neof\nc\n");
+    assert!(output.contains("waiting for input"));
+    assert!(output.contains("a\n\n"));
+    assert!(output.contains("halted after 4 steps"));
+}
+
+#[test]
+fn reports_unknown_commands() {
+    let (_, output) = debug("+", OptLevel::No, "jump\nquit\nstep\n");
+    assert!(output.contains("unknown command 'jump'"));
+    assert_eq!(output.matches("(bfi) ").count(), 2);
+}
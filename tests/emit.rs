@@ -0,0 +1,82 @@
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+
+use brainfuck::{emit_c, emit_rust, optimize, parse, run, ByteStream};
+use brainfuck::{Config, Encoding, OptLevel};
+
+/// Returns a path in a fresh temporary directory to emit `name` to.
+fn emit_path(name: &str) -> String {
+    let dir: PathBuf = env::temp_dir().join(format!("bfi-emit-{}-{}", name,
+                                                    std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    dir.join(name).to_str().unwrap().to_string()
+}
+
+/// Runs the compiled program at `path` and returns its output.
+fn run_compiled(path: &str) -> Vec<u8> {
+    let output = Command::new(path).stdin(Stdio::null()).output().unwrap();
+    assert!(output.status.success(), "{}",
+            String::from_utf8_lossy(&output.stderr));
+    output.stdout
+}
+
+#[test]
+fn emitted_code_matches_the_interpreter() {
+    // the copy loop is skipped at cell 0, where its target is off the tape
+    let source = &b"+.-[-<+>]."[..];
+    let ast = parse(&mut ByteStream::new(source)).unwrap();
+    let ast = optimize(OptLevel::Aggressive, &ast);
+    let config = Config { tape_size: 16, ..Default::default() };
+    let (expected, _, result) = run(&ast, &config, Encoding::Bytes, b"");
+    result.unwrap();
+
+    let path = emit_path("copy");
+    emit_rust(&path, &ast, &config).unwrap();
+    let status = Command::new("rustc")
+        .args(["--crate-name", "copy", "-o", &path, &format!("{}.rs", path)])
+        .status().unwrap();
+    assert!(status.success());
+    assert_eq!(run_compiled(&path), expected);
+
+    // C is only compiled where a compiler is installed
+    emit_c(&path, &ast, &config).unwrap();
+    let c_source = fs::read_to_string(format!("{}.c", path)).unwrap();
+    assert!(c_source.contains("if (mem[p] != 0) { mem[p - 1] ="));
+    let compiled = Command::new("cc")
+        .args(["-o", &path, &format!("{}.c", path)])
+        .status();
+    if let Ok(status) = compiled {
+        assert!(status.success());
+        assert_eq!(run_compiled(&path), expected);
+    }
+}
+
+#[test]
+fn emitted_code_compiles_without_warnings() {
+    let path = format!("{}/examples/hello.b", env!("CARGO_MANIFEST_DIR"));
+    let hello = fs::read(path).unwrap();
+    for (name, source) in [("hello", &hello[..]), ("echo", &b",[.,]"[..])] {
+        let ast = parse(&mut ByteStream::new(source)).unwrap();
+        for opt_level in [OptLevel::No, OptLevel::Aggressive] {
+            let ast = optimize(opt_level, &ast);
+            let path = emit_path(name);
+            emit_rust(&path, &ast, &Default::default()).unwrap();
+            let status = Command::new("rustc")
+                .args(["-D", "warnings", "--crate-name", name, "-o", &path,
+                       &format!("{}.rs", path)])
+                .status().unwrap();
+            assert!(status.success());
+
+            emit_c(&path, &ast, &Default::default()).unwrap();
+            let compiled = Command::new("cc")
+                .args(["-Wall", "-Werror", "-o", &path,
+                       &format!("{}.c", path)])
+                .status();
+            if let Ok(status) = compiled {
+                assert!(status.success());
+            }
+        }
+    }
+}
@@ -0,0 +1,213 @@
+use std::fs::File;
+
+use brainfuck::{eval_with, optimize, optimize_with, parse, parse_with, run};
+use brainfuck::{ByteStream, CellWidth, Config, Encoding, Eof, ErrorKind, Mem};
+use brainfuck::MemError;
+use brainfuck::{OptLevel, OptimizeOptions, OutOfBounds, Overflow};
+use brainfuck::{ParseOptions, Right, TapeMode};
+
+const OPT_LEVELS: [OptLevel; 4] = [OptLevel::No, OptLevel::Less,
+                                   OptLevel::Default, OptLevel::Aggressive];
+
+/// Runs `source` at every `OptLevel`, checking they all agree on the output
+/// and error, and returns the output along with the error, if any.
+fn run_source(source: &[u8], config: Config, encoding: Encoding,
+              input: &[u8]) -> (Vec<u8>, Option<ErrorKind>) {
+    let ast = parse(&mut ByteStream::new(source)).unwrap();
+    let options = OptimizeOptions {
+        overflow: config.overflow,
+        ..Default::default()
+    };
+    let mut results = OPT_LEVELS.iter().map(|&opt_level| {
+        let ast = optimize_with(opt_level, &ast, &options);
+        let (output, _, result) = run(&ast, &config, encoding, input);
+        (output, result.err().map(|e| e.error))
+    });
+    let first = results.next().unwrap();
+    for (output, error) in results {
+        assert_eq!(output, first.0);
+
+        // an ErrorKind may hold an io::Error, so compare how they print
+        assert_eq!(format!("{:?}", error), format!("{:?}", first.1));
+    }
+    first
+}
+
+fn run_bytes(source: &str, config: Config, input: &[u8]) -> Vec<u8> {
+    let (output, error) = run_source(source.as_bytes(), config,
+                                     Encoding::Bytes, input);
+    assert!(error.is_none(), "unexpected error: {:?}", error);
+    output
+}
+
+fn run_example(name: &str) -> Vec<u8> {
+    let path = format!("{}/examples/{}", env!("CARGO_MANIFEST_DIR"), name);
+    let ast = parse(&mut ByteStream::new(File::open(path).unwrap())).unwrap();
+    let ast = optimize(OptLevel::Aggressive, &ast);
+    let (output, _, result) = run(&ast, &Default::default(), Encoding::Bytes,
+                                  b"");
+    result.unwrap();
+    output
+}
+
+#[test]
+fn hello_world() {
+    assert_eq!(run_example("hello.b"), b"Hello World!\n".to_vec());
+}
+
+#[test]
+fn self_interpreter_with_separated_input() {
+    let path = format!("{}/examples/dbfi.b", env!("CARGO_MANIFEST_DIR"));
+    let mut source = std::fs::read(path).unwrap();
+    source.extend_from_slice(b"!,[.,]!echo");
+
+    let options = ParseOptions { separator: true, ..Default::default() };
+    let mut byte_stream = ByteStream::new(&source[..]);
+    let ast = parse_with(&mut byte_stream, &options).unwrap();
+    let input: Vec<u8> = byte_stream.collect();
+    assert_eq!(input, b",[.,]!echo".to_vec());
+
+    let config = Config { eof: Eof::Zero, ..Default::default() };
+    let ast = optimize(OptLevel::Aggressive, &ast);
+    let (output, _, result) = run(&ast, &config, Encoding::Bytes, &input);
+    result.unwrap();
+    assert_eq!(output, b"echo".to_vec());
+}
+
+#[test]
+fn returns_dumps_with_the_output() {
+    let options = ParseOptions { dump: true, ..Default::default() };
+    let ast = parse_with(&mut ByteStream::new(&b"+.#>."[..]), &options)
+        .unwrap();
+    let config = Config { tape_size: 2, ..Default::default() };
+    let (output, dumps, result) = run(&ast, &config, Encoding::Bytes, b"");
+    result.unwrap();
+    assert_eq!(output, vec![1, 0]);
+    assert_eq!(dumps, vec!["1:3: pointer at cell 0: [1] 0".to_string()]);
+}
+
+#[test]
+fn squares() {
+    let output = String::from_utf8(run_example("squares.b")).unwrap();
+    for (n, line) in output.lines().take(101).enumerate() {
+        assert_eq!(line, (n * n).to_string());
+    }
+}
+
+#[test]
+fn echoes_input() {
+    let config = Config { eof: Eof::Zero, ..Default::default() };
+    assert_eq!(run_bytes(",[.,]", config, b"abc"), b"abc".to_vec());
+}
+
+#[test]
+fn cells_wrap_at_their_width() {
+    let mut config: Config = Default::default();
+    assert_eq!(run_bytes("-.", config, b""), vec![255]);
+
+    // the low byte of a 16 bit cell holding 256 is 0
+    config.cell_width = CellWidth::W16;
+    assert_eq!(run_bytes("++++++++++++++++[>++++++++++++++++<-]>[>+<-]>.",
+                         config, b""),
+               vec![0]);
+    assert_eq!(run_bytes("-[>+<-----]>.", config, b""), vec![0x33]);
+}
+
+#[test]
+fn overflow_policies() {
+    let mut config = Config {
+        overflow: Overflow::Saturate,
+        ..Default::default()
+    };
+    assert_eq!(run_bytes("-.+++.", config, b""), vec![0, 3]);
+    assert_eq!(run_bytes("-+.>+[->+>++-<<]>.>.", config, b""),
+               vec![1, 1, 1]);
+
+    config.overflow = Overflow::Error;
+    let (output, error) = run_source(b"+.-.-.", config, Encoding::Bytes, b"");
+    assert_eq!(output, vec![1, 0]);
+    assert!(matches!(error, Some(ErrorKind::Mem(MemError::Underflow))));
+    let (_, error) = run_source(b"-+", config, Encoding::Bytes, b"");
+    assert!(matches!(error, Some(ErrorKind::Mem(MemError::Underflow))));
+    let (_, error) = run_source(b"+[+]", config, Encoding::Bytes, b"");
+    assert!(matches!(error, Some(ErrorKind::Mem(MemError::Overflow))));
+}
+
+#[test]
+fn out_of_bounds_policies() {
+    let mut config = Config { tape_size: 4, ..Default::default() };
+    let (_, error) = run_source(b"<", config, Encoding::Bytes, b"");
+    assert!(matches!(error, Some(ErrorKind::Mem(MemError::OutOfBounds(_)))));
+
+    config.out_of_bounds = OutOfBounds::Wrap;
+    assert_eq!(run_bytes("+>>>>.<.", config, b""), vec![1, 0]);
+
+    config.out_of_bounds = OutOfBounds::Clamp;
+    assert_eq!(run_bytes(">>>>>>+<<<.", config, b""), vec![0]);
+    assert_eq!(run_bytes(">>>>>>+.", config, b""), vec![1]);
+}
+
+#[test]
+fn growable_tape() {
+    let mut config = Config { tape_size: 2, ..Default::default() };
+    config.tape_mode = TapeMode::Growable { left: false, max: 16 };
+    assert_eq!(run_bytes(">>>>>>>>+.", config, b""), vec![1]);
+    let (_, error) = run_source(b"<", config, Encoding::Bytes, b"");
+    assert!(matches!(error, Some(ErrorKind::Mem(MemError::OutOfBounds(_)))));
+    let (_, error) = run_source(&[b'>'; 16], config, Encoding::Bytes, b"");
+    assert!(matches!(error,
+                     Some(ErrorKind::Mem(MemError::OutOfBounds(Right)))));
+
+    config.tape_mode = TapeMode::Growable { left: true, max: 16 };
+    assert_eq!(run_bytes("+<<<<+<<+.>>>>>>.", config, b""), vec![1, 1]);
+}
+
+#[test]
+fn evaluates_on_a_tape_kept_between_programs() {
+    let mut mem = Mem::new(&Default::default());
+    for (source, expected) in [("++>+", 1), ("<.", 2)] {
+        let ast = parse(&mut ByteStream::new(source.as_bytes())).unwrap();
+        let mut output = Vec::new();
+        eval_with(&ast, &mut mem, Encoding::Bytes, &mut &b""[..], &mut output)
+            .unwrap();
+        assert_eq!(mem.get(), expected);
+    }
+}
+
+#[test]
+fn empty_tape_holds_one_cell() {
+    let config = Config { tape_size: 0, ..Default::default() };
+    assert_eq!(run_bytes("+.", config, b""), vec![1]);
+    let (_, error) = run_source(b">", config, Encoding::Bytes, b"");
+    assert!(matches!(error, Some(ErrorKind::Mem(MemError::OutOfBounds(_)))));
+}
+
+#[test]
+fn eof_policies() {
+    let mut config = Config { eof: Eof::Unchanged, ..Default::default() };
+    assert_eq!(run_bytes("+++,.", config, b""), vec![3]);
+    config.eof = Eof::Zero;
+    assert_eq!(run_bytes("+++,.", config, b""), vec![0]);
+    config.eof = Eof::MinusOne;
+    assert_eq!(run_bytes("+++,.", config, b""), vec![255]);
+}
+
+#[test]
+fn utf8_encoding() {
+    let mut config = Config {
+        cell_width: CellWidth::W32,
+        ..Default::default()
+    };
+    config.eof = Eof::Zero;
+    let (output, error) = run_source(b",[.,]", config, Encoding::Utf8,
+                                     "h\u{e9}\u{1f600}".as_bytes());
+    assert!(error.is_none());
+    assert_eq!(output, "h\u{e9}\u{1f600}".as_bytes().to_vec());
+
+    // a code point above the largest char is written as U+FFFD
+    let (output, _) = run_source(b"-.", config, Encoding::Utf8, b"");
+    assert_eq!(output, "\u{fffd}".as_bytes().to_vec());
+
+    let (_, error) = run_source(b",", config, Encoding::Utf8, &[0xff]);
+    assert!(matches!(error, Some(ErrorKind::Io(_))));
+}
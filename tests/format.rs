@@ -0,0 +1,65 @@
+use brainfuck::{format, optimize, parse, ByteStream, FormatError, OptLevel};
+use brainfuck::{ParseOptions, parse_with};
+
+/// Formats `source` wrapped at `width` columns.
+fn fmt(source: &str, width: usize) -> String {
+    let ast = parse(&mut ByteStream::new(source.as_bytes())).unwrap();
+    format(&ast, width).unwrap()
+}
+
+#[test]
+fn removes_comments_and_wraps_lines() {
+    assert_eq!(fmt("a+b+c+ >>> [-] .", 80), "+++>>>[-].\n");
+    assert_eq!(fmt("++++++++", 3), "+++\n+++\n++\n");
+    assert_eq!(fmt("", 80), "");
+}
+
+#[test]
+fn splits_loops_which_do_not_fit() {
+    assert_eq!(fmt("++[>++[>+<-]<-]>.", 11),
+               "++\n\
+                [\n\
+                \x20 >++[>+<-]\n\
+                \x20 <-\n\
+                ]\n\
+                >.\n");
+    assert_eq!(fmt("++[>++[>+<-]<-]>.", 10),
+               "++\n\
+                [\n\
+                \x20 >++\n\
+                \x20 [\n\
+                \x20   >+<-\n\
+                \x20 ]\n\
+                \x20 <-\n\
+                ]\n\
+                >.\n");
+}
+
+#[test]
+fn keeps_dumps_when_parsed() {
+    let options = ParseOptions { dump: true, ..Default::default() };
+    let ast = parse_with(&mut ByteStream::new(&b"+#"[..]), &options).unwrap();
+    assert_eq!(format(&ast, 80).unwrap(), "+#\n");
+}
+
+#[test]
+fn fails_on_optimized_loops() {
+    let ast = parse(&mut ByteStream::new(&b"+[>+[->+<]<-]"[..])).unwrap();
+    let ast = optimize(OptLevel::Aggressive, &ast);
+    match format(&ast, 80) {
+        Err(FormatError::Optimized(span)) => {
+            assert_eq!(span.to_string(), "1:5-1:10");
+        },
+        result => panic!("expected a format error, found {:?}", result),
+    }
+}
+
+#[test]
+fn formatting_is_stable() {
+    let source = std::fs::read_to_string(concat!(env!("CARGO_MANIFEST_DIR"),
+                                                 "/examples/hanoi.b"))
+        .unwrap();
+    let once = fmt(&source, 40);
+    assert_eq!(fmt(&once, 40), once);
+    assert!(once.lines().all(|line| line.len() <= 40));
+}
@@ -0,0 +1,192 @@
+use brainfuck::{optimize, parse, parse_with, ByteStream, Config, Encoding};
+use brainfuck::{Eof, ErrorKind, Interpreter, Limits, MemError, OptLevel};
+use brainfuck::{OutOfBounds, Overflow, ParseOptions, Status};
+
+use std::io;
+use std::time::Duration;
+
+fn interpreter(source: &str, config: &Config, encoding: Encoding)
+               -> Interpreter {
+    let options = ParseOptions { dump: true, ..Default::default() };
+    let ast = parse_with(&mut ByteStream::new(source.as_bytes()), &options)
+                  .unwrap();
+    Interpreter::new(&ast, config, encoding)
+}
+
+#[test]
+fn steps_one_instruction_at_a_time() {
+    let mut interpreter = interpreter("++.", &Default::default(),
+                                      Encoding::Bytes);
+    assert!(matches!(interpreter.step(), Status::OutOfFuel));
+    assert_eq!(interpreter.pc(), 1);
+    assert_eq!(interpreter.mem().get(), 1);
+    assert!(matches!(interpreter.step(), Status::OutOfFuel));
+    assert!(matches!(interpreter.step(), Status::Halted));
+    assert!(interpreter.is_halted());
+    assert_eq!(interpreter.take_output(), vec![2]);
+    assert!(matches!(interpreter.step(), Status::Halted));
+}
+
+#[test]
+fn runs_for_a_number_of_steps_and_resumes() {
+    let mut interpreter = interpreter("+[]", &Default::default(),
+                                      Encoding::Bytes);
+    assert!(matches!(interpreter.run_for(1000), Status::OutOfFuel));
+    interpreter.mem_mut().set(0);
+    assert!(matches!(interpreter.run_for(1000), Status::Halted));
+}
+
+#[test]
+fn waits_for_input() {
+    let config = Config { eof: Eof::Zero, ..Default::default() };
+    let mut interpreter = interpreter(",[.,]", &config, Encoding::Bytes);
+    assert!(matches!(interpreter.run(), Status::NeedsInput));
+    assert_eq!(interpreter.pc(), 0);
+
+    interpreter.feed_input(b"ab");
+    assert!(matches!(interpreter.run(), Status::NeedsInput));
+    assert_eq!(interpreter.take_output(), b"ab".to_vec());
+
+    interpreter.feed_input(b"c");
+    interpreter.close_input();
+    assert!(matches!(interpreter.run(), Status::Halted));
+    assert_eq!(interpreter.take_output(), b"c".to_vec());
+}
+
+#[test]
+fn waits_for_a_whole_char_of_input() {
+    let mut interpreter = interpreter(",.", &Default::default(),
+                                      Encoding::Utf8);
+    let bytes = "\u{e9}".as_bytes();
+    interpreter.feed_input(&bytes[..1]);
+    assert!(matches!(interpreter.run(), Status::NeedsInput));
+    interpreter.feed_input(&bytes[1..]);
+    assert!(matches!(interpreter.run(), Status::Halted));
+    assert_eq!(interpreter.take_output(), bytes.to_vec());
+}
+
+#[test]
+fn stops_at_errors() {
+    let config = Config { overflow: Overflow::Error, ..Default::default() };
+    let mut interpreter = interpreter("+\n--", &config, Encoding::Bytes);
+    match interpreter.run() {
+        Status::Error(e) => {
+            assert!(matches!(e.error, ErrorKind::Mem(MemError::Underflow)));
+            assert_eq!(e.span.to_string(), "2:2");
+        },
+        status => panic!("expected an error, found {:?}", status),
+    }
+    assert_eq!(interpreter.pc(), 2);
+}
+
+#[test]
+fn runs_with_a_reader_and_writer() {
+    let config = Config { eof: Eof::Zero, ..Default::default() };
+    let mut interpreter = interpreter(",[.,]", &config, Encoding::Bytes);
+    let mut output = Vec::new();
+    interpreter.run_with(&mut &b"echo"[..], &mut output).unwrap();
+    assert_eq!(output, b"echo".to_vec());
+}
+
+#[test]
+fn stops_at_the_step_limit() {
+    let mut interpreter = interpreter("+[]", &Default::default(),
+                                      Encoding::Bytes);
+    interpreter.set_limits(Limits { max_steps: Some(100), ..Default::default() });
+    let error = interpreter.run_with(&mut io::empty(), &mut io::sink())
+                           .unwrap_err();
+    assert!(matches!(error.error, ErrorKind::StepLimit(100)));
+    assert!(error.error.is_limit());
+    assert_eq!(interpreter.steps(), 100);
+    assert_eq!(error.span, interpreter.span().unwrap());
+}
+
+#[test]
+fn scans_which_never_end_stop_at_the_limits() {
+    for &out_of_bounds in &[OutOfBounds::Clamp, OutOfBounds::Wrap] {
+        let config = Config { tape_size: 4, out_of_bounds,
+                              ..Default::default() };
+        let source = &b"+>+>+>+[<]"[..]; // no cell is left 0
+        let ast = parse(&mut ByteStream::new(source)).unwrap();
+        let ast = optimize(OptLevel::Default, &ast);
+        let mut interpreter = Interpreter::new(&ast, &config, Encoding::Bytes);
+        interpreter.set_limits(Limits { max_steps: Some(100),
+                                        ..Default::default() });
+        let error = interpreter.run_with(&mut io::empty(), &mut io::sink())
+                               .unwrap_err();
+        assert!(matches!(error.error, ErrorKind::StepLimit(100)));
+        assert_eq!(interpreter.pc(), interpreter.code().len() - 1);
+    }
+}
+
+#[test]
+fn halting_at_the_step_limit_succeeds() {
+    let mut interpreter = interpreter("+++", &Default::default(),
+                                      Encoding::Bytes);
+    interpreter.set_limits(Limits { max_steps: Some(3), ..Default::default() });
+    assert!(interpreter.run_with(&mut io::empty(), &mut io::sink()).is_ok());
+}
+
+#[test]
+fn stops_at_the_timeout() {
+    let mut interpreter = interpreter("+[]", &Default::default(),
+                                      Encoding::Bytes);
+    let timeout = Duration::from_millis(10);
+    interpreter.set_limits(Limits {
+        timeout: Some(timeout),
+        ..Default::default()
+    });
+    let error = interpreter.run_with(&mut io::empty(), &mut io::sink())
+                           .unwrap_err();
+    assert!(matches!(error.error, ErrorKind::Timeout(t) if t == timeout));
+}
+
+#[test]
+fn stops_at_the_input_limit() {
+    let mut interpreter = interpreter(",.,.,.", &Default::default(),
+                                      Encoding::Bytes);
+    interpreter.set_limits(Limits { max_input: Some(2), ..Default::default() });
+    interpreter.feed_input(b"abc");
+    match interpreter.run() {
+        Status::Error(e) => {
+            assert!(matches!(e.error, ErrorKind::InputLimit(2)));
+            assert_eq!(e.span.to_string(), "1:5");
+        },
+        status => panic!("expected an error, found {:?}", status),
+    }
+    assert_eq!(interpreter.take_output(), b"ab".to_vec());
+}
+
+#[test]
+fn stops_at_the_output_limit() {
+    let mut interpreter = interpreter("+[.]", &Default::default(),
+                                      Encoding::Utf8);
+    interpreter.set_limits(Limits { max_output: Some(3), ..Default::default() });
+    let mut output = Vec::new();
+    let error = interpreter.run_with(&mut io::empty(), &mut output)
+                           .unwrap_err();
+    assert!(matches!(error.error, ErrorKind::OutputLimit(3)));
+    assert_eq!(error.to_string(), "1:3: output limit of 3 bytes exceeded");
+    assert_eq!(output, vec![1, 1, 1]);
+}
+
+#[test]
+fn dumps_the_cells_around_the_pointer() {
+    let config = Config { tape_size: 4, ..Default::default() };
+    let mut interpreter = interpreter("+>++#>>#", &config, Encoding::Bytes);
+    assert!(matches!(interpreter.run(), Status::Halted));
+    assert_eq!(interpreter.take_dumps(),
+               vec!["1:5: pointer at cell 1: 1 [2] 0 0".to_string(),
+                    "1:8: pointer at cell 3: 1 2 0 [0]".to_string()]);
+    assert!(interpreter.take_dumps().is_empty());
+}
+
+#[test]
+fn counts_the_executions_of_each_instruction() {
+    let mut interpreter = interpreter("++[>+<-]", &Default::default(),
+                                      Encoding::Bytes);
+    assert!(interpreter.profile().is_none());
+    interpreter.enable_profile();
+    assert!(matches!(interpreter.run(), Status::Halted));
+    assert_eq!(interpreter.profile().unwrap(), &[1, 1, 1, 2, 2, 2, 2, 2]);
+}
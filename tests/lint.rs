@@ -0,0 +1,30 @@
+use brainfuck::{lint, parse, ByteStream, Warning};
+
+/// Lints `source`, returning each warning and the span it was found in.
+fn warnings(source: &str) -> Vec<(Warning, String)> {
+    let ast = parse(&mut ByteStream::new(source.as_bytes())).unwrap();
+    lint(&ast).into_iter()
+              .map(|lint| (lint.warning, lint.span.to_string()))
+              .collect()
+}
+
+#[test]
+fn finds_dead_loops() {
+    assert_eq!(warnings("[comment]+[-].[>]>[<]"),
+               vec![(Warning::DeadLoop, "1:1-1:9".to_string()),
+                    (Warning::DeadLoop, "1:15-1:17".to_string())]);
+    assert!(warnings("+[-]>[-]").is_empty());
+}
+
+#[test]
+fn finds_empty_loops() {
+    assert_eq!(warnings("+[[]-]"),
+               vec![(Warning::EmptyLoop, "1:3-1:4".to_string())]);
+}
+
+#[test]
+fn finds_instructions_which_cancel_out() {
+    assert_eq!(warnings("++-+ >.<,<<>>"),
+               vec![(Warning::Cancelling, "1:1-1:4".to_string()),
+                    (Warning::Cancelling, "1:10-1:13".to_string())]);
+}
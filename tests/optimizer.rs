@@ -0,0 +1,88 @@
+use brainfuck::{optimize_with, parse_with, ByteStream, Ir, Left, OptLevel};
+use brainfuck::{OptimizeOptions, Overflow, ParseOptions, Right};
+
+fn irs_with(source: &str, opt_level: OptLevel, options: &OptimizeOptions)
+            -> Vec<Ir> {
+    let parse_options = ParseOptions { dump: true, ..Default::default() };
+    let ast = parse_with(&mut ByteStream::new(source.as_bytes()),
+                         &parse_options).unwrap();
+    optimize_with(opt_level, &ast, options).into_iter()
+                                           .map(|node| node.ir)
+                                           .collect()
+}
+
+fn irs(source: &str, opt_level: OptLevel) -> Vec<Ir> {
+    irs_with(source, opt_level, &Default::default())
+}
+
+#[test]
+fn no_optimization_keeps_the_ast() {
+    assert_eq!(irs("[-]++", OptLevel::No).len(), 3);
+}
+
+#[test]
+fn removes_comment_and_unused_loops() {
+    let irs = irs("[.]+[-][.]", OptLevel::Less);
+    assert_eq!(irs.len(), 2);
+    assert_eq!(irs[0], Ir::Add(1));
+    match irs[1] {
+        Ir::Loop(ref body) => assert_eq!(body[0].ir, Ir::Sub(1)),
+        ref ir => panic!("expected a loop, found {:?}", ir),
+    }
+}
+
+#[test]
+fn contracts_runs() {
+    assert_eq!(irs(">>>+++<<<---", OptLevel::Default),
+               vec![Ir::Shift(Right, 3), Ir::Add(3), Ir::Shift(Left, 3),
+                    Ir::Sub(3)]);
+    assert_eq!(irs("+-<>", OptLevel::Default), vec![]);
+}
+
+#[test]
+fn keeps_what_only_holds_when_cells_wrap() {
+    let options = OptimizeOptions {
+        overflow: Overflow::Saturate,
+        ..Default::default()
+    };
+    assert_eq!(irs_with("-+<>", OptLevel::Default, &options),
+               vec![Ir::Sub(1), Ir::Add(1)]);
+    let irs = irs_with("+[-]>[+]", OptLevel::Default, &options);
+    assert_eq!(irs[..3], [Ir::Add(1), Ir::Clear, Ir::Shift(Right, 1)]);
+    match irs[3] {
+        Ir::Loop(ref body) => assert_eq!(body[0].ir, Ir::Add(1)),
+        ref ir => panic!("expected a loop, found {:?}", ir),
+    }
+}
+
+#[test]
+fn replaces_clear_and_scan_loops() {
+    assert_eq!(irs("+[-]>[+]>[<]", OptLevel::Default),
+               vec![Ir::Add(1), Ir::Clear, Ir::Shift(Right, 1), Ir::Clear,
+                    Ir::Shift(Right, 1), Ir::Scan(Left)]);
+}
+
+#[test]
+fn replaces_copy_and_mul_loops() {
+    assert_eq!(irs("+[->+>++>--<<<]", OptLevel::Aggressive),
+               vec![Ir::Add(1), Ir::Copy(Right, 1), Ir::Mul(Right, 2, 2),
+                    Ir::Mul(Right, 3, -2), Ir::Clear]);
+}
+
+#[test]
+fn keeps_loops_that_move_the_pointer() {
+    let irs = irs("+[->+]", OptLevel::Aggressive);
+    assert_eq!(irs.len(), 2);
+    assert!(matches!(irs[1], Ir::Loop(_)));
+}
+
+#[test]
+fn dumps_are_barriers() {
+    assert_eq!(irs("+#-", OptLevel::Aggressive),
+               vec![Ir::Add(1), Ir::Dump, Ir::Sub(1)]);
+    let irs = irs("+[-#]", OptLevel::Aggressive);
+    match irs[1] {
+        Ir::Loop(ref body) => assert_eq!(body[1].ir, Ir::Dump),
+        ref ir => panic!("expected a loop, found {:?}", ir),
+    }
+}
@@ -0,0 +1,101 @@
+use brainfuck::{parse, parse_with, ByteStream, Error, Ir, Left, ParseError};
+use brainfuck::{ParseOptions, Position, Right};
+
+fn pos(offset: usize, line: usize, col: usize) -> Position {
+    Position { offset, line, col }
+}
+
+/// Parses `source`, which must fail with a `ParseError`, and returns it.
+fn parse_error(source: &[u8]) -> ParseError {
+    match parse(&mut ByteStream::new(source)) {
+        Err(Error::Parse(e)) => e,
+        result => panic!("expected a parse error, found {:?}", result),
+    }
+}
+
+#[test]
+fn parses_every_instruction() {
+    let ast = parse(&mut ByteStream::new(&b"+-<>,.[]"[..])).unwrap();
+    let irs: Vec<Ir> = ast.into_iter().map(|node| node.ir).collect();
+    assert_eq!(irs, vec![Ir::Add(1), Ir::Sub(1), Ir::Shift(Left, 1),
+                         Ir::Shift(Right, 1), Ir::Read, Ir::Write,
+                         Ir::Loop(Vec::new())]);
+}
+
+#[test]
+fn ignores_comments() {
+    let ast = parse(&mut ByteStream::new(&b"a + b\n- c"[..])).unwrap();
+    assert_eq!(ast.len(), 2);
+    assert_eq!(ast[1].span.start, pos(6, 2, 1));
+}
+
+#[test]
+fn nests_loops_with_spans() {
+    let ast = parse(&mut ByteStream::new(&b"[\n[+]]"[..])).unwrap();
+    assert_eq!(ast.len(), 1);
+    assert_eq!(ast[0].span.start, pos(0, 1, 1));
+    assert_eq!(ast[0].span.end, pos(5, 2, 4));
+    match ast[0].ir {
+        Ir::Loop(ref body) => {
+            assert_eq!(body.len(), 1);
+            assert_eq!(body[0].span.start, pos(2, 2, 1));
+        },
+        ref ir => panic!("expected a loop, found {:?}", ir),
+    }
+}
+
+#[test]
+fn reports_unmatched_open() {
+    assert_eq!(parse_error(b"[[]\n+"),
+               ParseError::UnmatchedOpen(pos(0, 1, 1), Some(pos(2, 1, 3))));
+}
+
+#[test]
+fn reports_unmatched_close() {
+    let error = parse_error(b"+\n[]]");
+    assert_eq!(error, ParseError::UnmatchedClose(pos(4, 2, 3),
+                                                 Some(pos(2, 2, 1))));
+    assert_eq!(error.position().to_string(), "2:3");
+    assert_eq!(error.to_string(), "unmatched ']' (nearest '[' is at 2:1)");
+}
+
+#[test]
+fn parses_dumps_when_asked() {
+    let ast = parse(&mut ByteStream::new(&b"+#"[..])).unwrap();
+    assert_eq!(ast.len(), 1);
+
+    let options = ParseOptions { dump: true, ..Default::default() };
+    let ast = parse_with(&mut ByteStream::new(&b"+#"[..]), &options).unwrap();
+    assert_eq!(ast[1].ir, Ir::Dump);
+    assert_eq!(ast[1].span.start, pos(1, 1, 2));
+}
+
+#[test]
+fn stops_at_the_separator_when_asked() {
+    let mut byte_stream = ByteStream::new(&b"+!-!"[..]);
+    let ast = parse(&mut byte_stream).unwrap();
+    assert_eq!(ast.len(), 2);
+
+    let options = ParseOptions { separator: true, ..Default::default() };
+    let mut byte_stream = ByteStream::new(&b"+!-!"[..]);
+    let ast = parse_with(&mut byte_stream, &options).unwrap();
+    assert_eq!(ast.len(), 1);
+    assert_eq!(byte_stream.collect::<Vec<u8>>(), b"-!".to_vec());
+}
+
+#[test]
+fn fails_at_a_read_error() {
+    struct Failing;
+    impl std::io::Read for Failing {
+        fn read(&mut self, _: &mut [u8]) -> std::io::Result<usize> {
+            Err(std::io::Error::other("disk on fire"))
+        }
+    }
+
+    // the loop left open is cut off by the error, rather than unmatched
+    let reader = std::io::Read::chain(&b"[+"[..], Failing);
+    match parse(&mut ByteStream::new(reader)) {
+        Err(Error::Io(e)) => assert_eq!(e.to_string(), "disk on fire"),
+        result => panic!("expected a read error, found {:?}", result),
+    }
+}
@@ -0,0 +1,72 @@
+use brainfuck::{Encoding, OptLevel, ParseOptions, Repl};
+
+/// Runs `lines` in a new REPL optimizing at `opt_level`, and returns the REPL
+/// and everything it wrote.
+fn repl(lines: &str, opt_level: OptLevel) -> (Repl, String) {
+    let mut repl = Repl::new(&Default::default(), Encoding::Bytes,
+                             opt_level, Default::default());
+    let mut output = Vec::new();
+    repl.run(&mut lines.as_bytes(), &mut output).unwrap();
+    (repl, String::from_utf8(output).unwrap())
+}
+
+#[test]
+fn keeps_the_tape_between_lines() {
+    let (repl, output) = repl("+++>++\n<-\n", OptLevel::Default);
+    assert_eq!(repl.interpreter().mem().position(), 0);
+    assert_eq!(repl.interpreter().mem().get(), 2);
+    assert_eq!(output, "bf> pointer at cell 1: 3 [2] 0 0 0 0 0 0 0 0\n\
+                        bf> pointer at cell 0: [2] 2 0 0 0 0 0 0 0\n\
+                        bf> \n");
+}
+
+#[test]
+fn waits_for_loops_to_close() {
+    let (repl, output) = repl("++[\n>+++<\n-]>.\n", OptLevel::Aggressive);
+    assert_eq!(repl.interpreter().mem().get(), 6);
+    assert_eq!(output, "bf> ... ... \u{6}\n\
+                        pointer at cell 1: 0 [6] 0 0 0 0 0 0 0 0\n\
+                        bf> \n");
+}
+
+#[test]
+fn reads_input_from_the_next_line() {
+    let (repl, output) = repl(",.,.\nhi\n,.\n", OptLevel::No);
+    assert!(output.starts_with("bf> input> hi\npointer at cell 0: [105]"));
+    assert!(output.contains("bf> \npointer at cell 0: [10]"));
+    assert_eq!(repl.interpreter().mem().get(), 10);
+}
+
+#[test]
+fn reports_errors_and_carries_on() {
+    let (repl, output) = repl("+]\n<\n+\n", OptLevel::No);
+    assert!(output.contains("error: 1:2: unmatched ']'\n"));
+    assert!(output.contains("error: 1:1: pointer moved off the left end of \
+                             the tape\n"));
+    assert_eq!(repl.interpreter().mem().get(), 1);
+}
+
+#[test]
+fn runs_loops_at_the_start_of_a_line() {
+    for &opt_level in &[OptLevel::Less, OptLevel::Aggressive] {
+        let (repl, output) = repl("+++\n[->+<]\n", opt_level);
+        assert_eq!(repl.interpreter().mem().position(), 0);
+        assert!(output.contains("bf> pointer at cell 0: [0] 3 0"));
+    }
+}
+
+#[test]
+fn writes_dumps_where_they_are_made() {
+    let options = ParseOptions { dump: true, ..Default::default() };
+    let mut repl = Repl::new(&Default::default(), Encoding::Bytes,
+                             OptLevel::No, options);
+    let mut output = Vec::new();
+    repl.run(&mut &b"+++++[>++++++++++++<-]>+.#.\n"[..], &mut output)
+        .unwrap();
+    assert_eq!(String::from_utf8(output).unwrap(),
+               "bf> =\n\
+                1:26: pointer at cell 1: 0 [61] 0 0 0 0 0 0 0 0\n\
+                =\n\
+                pointer at cell 1: 0 [61] 0 0 0 0 0 0 0 0\n\
+                bf> \n");
+}